@@ -0,0 +1,13 @@
+-- Your SQL goes here
+
+
+CREATE TABLE `route_checks`(
+	`id` INTEGER PRIMARY KEY,
+	`kind` TEXT NOT NULL,
+	`route_id` INTEGER NOT NULL,
+	`checked_at` BIGINT NOT NULL,
+	`success` BOOL NOT NULL,
+	`latency_ms` INTEGER,
+	`error` TEXT,
+	UNIQUE(`kind`, `route_id`)
+);
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+
+
+ALTER TABLE `https_routes` DROP COLUMN `tls_domains`;
+ALTER TABLE `https_routes` DROP COLUMN `cert_resolver`;
@@ -0,0 +1,5 @@
+-- Your SQL goes here
+
+
+ALTER TABLE `https_routes` ADD COLUMN `cert_resolver` TEXT;
+ALTER TABLE `https_routes` ADD COLUMN `tls_domains` TEXT;
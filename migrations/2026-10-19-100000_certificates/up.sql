@@ -0,0 +1,10 @@
+-- Your SQL goes here
+
+
+CREATE TABLE `certificates`(
+	`id` INTEGER PRIMARY KEY,
+	`name` TEXT NOT NULL,
+	`cert_pem` TEXT NOT NULL,
+	`key_pem` TEXT NOT NULL,
+	`default_certificate` BOOL NOT NULL
+);
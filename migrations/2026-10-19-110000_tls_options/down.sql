@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+
+
+ALTER TABLE `https_routes` DROP COLUMN `tls_options`;
+
+DROP TABLE IF EXISTS `tls_options`;
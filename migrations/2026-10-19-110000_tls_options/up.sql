@@ -0,0 +1,14 @@
+-- Your SQL goes here
+
+
+CREATE TABLE `tls_options`(
+	`id` INTEGER PRIMARY KEY,
+	`name` TEXT NOT NULL UNIQUE,
+	`min_version` TEXT,
+	`cipher_suites` TEXT,
+	`sni_strict` BOOL NOT NULL,
+	`client_auth_type` TEXT,
+	`client_ca` TEXT
+);
+
+ALTER TABLE `https_routes` ADD COLUMN `tls_options` TEXT;
@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+
+
+ALTER TABLE `tls_routes` DROP COLUMN `tls_options`;
+ALTER TABLE `tls_routes` DROP COLUMN `cert_resolver`;
+ALTER TABLE `tls_routes` DROP COLUMN `terminate_tls`;
@@ -0,0 +1,6 @@
+-- Your SQL goes here
+
+
+ALTER TABLE `tls_routes` ADD COLUMN `terminate_tls` BOOL NOT NULL DEFAULT FALSE;
+ALTER TABLE `tls_routes` ADD COLUMN `cert_resolver` TEXT;
+ALTER TABLE `tls_routes` ADD COLUMN `tls_options` TEXT;
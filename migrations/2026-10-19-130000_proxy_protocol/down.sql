@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+
+
+ALTER TABLE `tls_routes` DROP COLUMN `proxy_protocol`;
@@ -0,0 +1,4 @@
+-- Your SQL goes here
+
+
+ALTER TABLE `tls_routes` ADD COLUMN `proxy_protocol` INTEGER;
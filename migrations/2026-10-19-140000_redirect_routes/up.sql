@@ -0,0 +1,15 @@
+-- Your SQL goes here
+
+
+CREATE TABLE `redirect_routes`(
+	`id` INTEGER PRIMARY KEY,
+	`enabled` BOOL NOT NULL,
+	`name` TEXT NOT NULL,
+	`priority` INTEGER,
+	`host` TEXT NOT NULL,
+	`destination` TEXT NOT NULL,
+	`preserve_path` BOOL NOT NULL,
+	`permanent` BOOL NOT NULL,
+	`on_http` BOOL NOT NULL,
+	`on_https` BOOL NOT NULL
+);
@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+
+
+ALTER TABLE `https_routes` DROP COLUMN `maintenance_html`;
+ALTER TABLE `https_routes` DROP COLUMN `maintenance`;
+ALTER TABLE `http_routes` DROP COLUMN `maintenance_html`;
+ALTER TABLE `http_routes` DROP COLUMN `maintenance`;
@@ -0,0 +1,7 @@
+-- Your SQL goes here
+
+
+ALTER TABLE `http_routes` ADD COLUMN `maintenance` BOOL NOT NULL DEFAULT FALSE;
+ALTER TABLE `http_routes` ADD COLUMN `maintenance_html` TEXT;
+ALTER TABLE `https_routes` ADD COLUMN `maintenance` BOOL NOT NULL DEFAULT FALSE;
+ALTER TABLE `https_routes` ADD COLUMN `maintenance_html` TEXT;
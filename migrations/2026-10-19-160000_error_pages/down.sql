@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+
+
+ALTER TABLE `https_routes` DROP COLUMN `error_page`;
+ALTER TABLE `http_routes` DROP COLUMN `error_page`;
+
+DROP TABLE IF EXISTS `error_pages`;
@@ -0,0 +1,13 @@
+-- Your SQL goes here
+
+
+CREATE TABLE `error_pages`(
+	`id` INTEGER PRIMARY KEY,
+	`name` TEXT NOT NULL UNIQUE,
+	`status` TEXT NOT NULL,
+	`html` TEXT NOT NULL,
+	`global` BOOL NOT NULL
+);
+
+ALTER TABLE `http_routes` ADD COLUMN `error_page` TEXT;
+ALTER TABLE `https_routes` ADD COLUMN `error_page` TEXT;
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+
+
+ALTER TABLE `https_routes` DROP COLUMN `skip_default_middlewares`;
+ALTER TABLE `http_routes` DROP COLUMN `skip_default_middlewares`;
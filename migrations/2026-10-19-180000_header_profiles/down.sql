@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+
+
+ALTER TABLE `https_routes` DROP COLUMN `header_profile`;
+
+DROP TABLE IF EXISTS `header_profiles`;
@@ -0,0 +1,23 @@
+-- Your SQL goes here
+
+
+CREATE TABLE `header_profiles`(
+	`id` INTEGER PRIMARY KEY,
+	`name` TEXT NOT NULL UNIQUE,
+	`sts_seconds` INTEGER,
+	`sts_include_subdomains` BOOL NOT NULL,
+	`sts_preload` BOOL NOT NULL,
+	`frame_deny` BOOL NOT NULL,
+	`content_type_nosniff` BOOL NOT NULL,
+	`browser_xss_filter` BOOL NOT NULL,
+	`referrer_policy` TEXT,
+	`content_security_policy` TEXT,
+	`custom_request_headers` TEXT,
+	`custom_response_headers` TEXT,
+	`cors_allow_origins` TEXT,
+	`cors_allow_methods` TEXT,
+	`cors_allow_headers` TEXT,
+	`cors_allow_credentials` BOOL NOT NULL
+);
+
+ALTER TABLE `https_routes` ADD COLUMN `header_profile` TEXT;
@@ -0,0 +1,11 @@
+-- This file should undo anything in `up.sql`
+
+
+ALTER TABLE `https_routes` DROP COLUMN `path_replacement`;
+ALTER TABLE `https_routes` DROP COLUMN `path_regex`;
+ALTER TABLE `https_routes` DROP COLUMN `add_prefix`;
+ALTER TABLE `https_routes` DROP COLUMN `strip_prefix`;
+ALTER TABLE `http_routes` DROP COLUMN `path_replacement`;
+ALTER TABLE `http_routes` DROP COLUMN `path_regex`;
+ALTER TABLE `http_routes` DROP COLUMN `add_prefix`;
+ALTER TABLE `http_routes` DROP COLUMN `strip_prefix`;
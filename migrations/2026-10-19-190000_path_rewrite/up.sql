@@ -0,0 +1,11 @@
+-- Your SQL goes here
+
+
+ALTER TABLE `http_routes` ADD COLUMN `strip_prefix` BOOL NOT NULL DEFAULT FALSE;
+ALTER TABLE `http_routes` ADD COLUMN `add_prefix` TEXT;
+ALTER TABLE `http_routes` ADD COLUMN `path_regex` TEXT;
+ALTER TABLE `http_routes` ADD COLUMN `path_replacement` TEXT;
+ALTER TABLE `https_routes` ADD COLUMN `strip_prefix` BOOL NOT NULL DEFAULT FALSE;
+ALTER TABLE `https_routes` ADD COLUMN `add_prefix` TEXT;
+ALTER TABLE `https_routes` ADD COLUMN `path_regex` TEXT;
+ALTER TABLE `https_routes` ADD COLUMN `path_replacement` TEXT;
@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+
+
+ALTER TABLE `https_routes` DROP COLUMN `auth_realm`;
+ALTER TABLE `http_routes` DROP COLUMN `auth_realm`;
+
+DROP TABLE IF EXISTS `auth_realms`;
@@ -0,0 +1,13 @@
+-- Your SQL goes here
+
+
+CREATE TABLE `auth_realms`(
+	`id` INTEGER PRIMARY KEY,
+	`name` TEXT NOT NULL UNIQUE,
+	`digest` BOOL NOT NULL,
+	`remove_header` BOOL NOT NULL,
+	`users` TEXT NOT NULL
+);
+
+ALTER TABLE `http_routes` ADD COLUMN `auth_realm` TEXT;
+ALTER TABLE `https_routes` ADD COLUMN `auth_realm` TEXT;
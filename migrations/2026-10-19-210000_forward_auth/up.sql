@@ -0,0 +1,5 @@
+-- Your SQL goes here
+
+
+ALTER TABLE `http_routes` ADD COLUMN `forward_auth` BOOL NOT NULL DEFAULT FALSE;
+ALTER TABLE `https_routes` ADD COLUMN `forward_auth` BOOL NOT NULL DEFAULT FALSE;
@@ -0,0 +1,17 @@
+-- This file should undo anything in `up.sql`
+
+
+ALTER TABLE `tls_routes` DROP COLUMN `ip_allow_list`;
+ALTER TABLE `tls_routes` DROP COLUMN `in_flight_connections`;
+ALTER TABLE `https_routes` DROP COLUMN `in_flight_requests`;
+ALTER TABLE `https_routes` DROP COLUMN `limit_header`;
+ALTER TABLE `https_routes` DROP COLUMN `limit_source`;
+ALTER TABLE `https_routes` DROP COLUMN `rate_limit_period`;
+ALTER TABLE `https_routes` DROP COLUMN `rate_limit_burst`;
+ALTER TABLE `https_routes` DROP COLUMN `rate_limit_average`;
+ALTER TABLE `http_routes` DROP COLUMN `in_flight_requests`;
+ALTER TABLE `http_routes` DROP COLUMN `limit_header`;
+ALTER TABLE `http_routes` DROP COLUMN `limit_source`;
+ALTER TABLE `http_routes` DROP COLUMN `rate_limit_period`;
+ALTER TABLE `http_routes` DROP COLUMN `rate_limit_burst`;
+ALTER TABLE `http_routes` DROP COLUMN `rate_limit_average`;
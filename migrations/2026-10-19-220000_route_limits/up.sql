@@ -0,0 +1,17 @@
+-- Your SQL goes here
+
+
+ALTER TABLE `http_routes` ADD COLUMN `rate_limit_average` INTEGER;
+ALTER TABLE `http_routes` ADD COLUMN `rate_limit_burst` INTEGER;
+ALTER TABLE `http_routes` ADD COLUMN `rate_limit_period` INTEGER;
+ALTER TABLE `http_routes` ADD COLUMN `limit_source` TEXT;
+ALTER TABLE `http_routes` ADD COLUMN `limit_header` TEXT;
+ALTER TABLE `http_routes` ADD COLUMN `in_flight_requests` INTEGER;
+ALTER TABLE `https_routes` ADD COLUMN `rate_limit_average` INTEGER;
+ALTER TABLE `https_routes` ADD COLUMN `rate_limit_burst` INTEGER;
+ALTER TABLE `https_routes` ADD COLUMN `rate_limit_period` INTEGER;
+ALTER TABLE `https_routes` ADD COLUMN `limit_source` TEXT;
+ALTER TABLE `https_routes` ADD COLUMN `limit_header` TEXT;
+ALTER TABLE `https_routes` ADD COLUMN `in_flight_requests` INTEGER;
+ALTER TABLE `tls_routes` ADD COLUMN `in_flight_connections` INTEGER;
+ALTER TABLE `tls_routes` ADD COLUMN `ip_allow_list` TEXT;
@@ -0,0 +1,4 @@
+-- Your SQL goes here
+
+
+ALTER TABLE `https_routes` ADD COLUMN `mirrors` TEXT;
@@ -0,0 +1,9 @@
+-- This file should undo anything in `up.sql`
+
+
+ALTER TABLE `https_routes` DROP COLUMN `health_check_interval`;
+ALTER TABLE `https_routes` DROP COLUMN `health_check_path`;
+ALTER TABLE `https_routes` DROP COLUMN `fallback_target`;
+ALTER TABLE `http_routes` DROP COLUMN `health_check_interval`;
+ALTER TABLE `http_routes` DROP COLUMN `health_check_path`;
+ALTER TABLE `http_routes` DROP COLUMN `fallback_target`;
@@ -0,0 +1,9 @@
+-- Your SQL goes here
+
+
+ALTER TABLE `http_routes` ADD COLUMN `fallback_target` TEXT;
+ALTER TABLE `http_routes` ADD COLUMN `health_check_path` TEXT;
+ALTER TABLE `http_routes` ADD COLUMN `health_check_interval` INTEGER;
+ALTER TABLE `https_routes` ADD COLUMN `fallback_target` TEXT;
+ALTER TABLE `https_routes` ADD COLUMN `health_check_path` TEXT;
+ALTER TABLE `https_routes` ADD COLUMN `health_check_interval` INTEGER;
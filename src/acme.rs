@@ -0,0 +1,250 @@
+//! Inventory of the certificates traefik stored in its `acme.json`.
+
+use std::collections::BTreeMap;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use rocket::State;
+use rocket_dyn_templates::Template;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    certificates,
+    config::ConfigState,
+    https::HttpsRoute,
+    x509::{CertificateInfo, EXPIRY_WARNING_DAYS},
+    DbConn,
+};
+
+#[derive(Debug, Error)]
+pub enum AcmeError {
+    #[error("no acme.json configured, set its path in the config page")]
+    NotConfigured,
+    #[error("error reading {0}: {1}")]
+    Read(String, std::io::Error),
+    #[error("error parsing acme.json: {0}")]
+    Parse(#[from] serde_json::Error),
+}
+
+#[derive(Deserialize)]
+struct AcmeResolver {
+    #[serde(rename = "Certificates", default)]
+    certificates: Option<Vec<AcmeCertificate>>,
+}
+
+#[derive(Deserialize)]
+struct AcmeCertificate {
+    domain: AcmeDomain,
+    /// base64 encoded PEM chain
+    certificate: String,
+}
+
+#[derive(Deserialize)]
+struct AcmeDomain {
+    main: String,
+    #[serde(default)]
+    sans: Option<Vec<String>>,
+}
+
+#[derive(Serialize)]
+pub struct Certificate {
+    pub resolver: String,
+    pub main: String,
+    pub sans: Vec<String>,
+    pub info: Option<CertificateInfo>,
+    pub error: Option<String>,
+    /// names of the HTTPS routes using this certificate
+    pub routes: Vec<String>,
+}
+
+/// Whether the hosts of an HTTPS route are covered by a certificate
+#[derive(Serialize)]
+pub struct RouteCoverage {
+    pub name: String,
+    pub host: String,
+    /// `ok`, `expiring`, `missing`, `uploaded` if an uploaded certificate covers the hosts,
+    /// `no-resolver` if the route doesn't use a resolver or `regex` if the hosts can't be checked
+    pub state: &'static str,
+    pub days_left: Option<i64>,
+}
+
+pub fn load(path: &str) -> Result<Vec<Certificate>, AcmeError> {
+    if path.is_empty() {
+        return Err(AcmeError::NotConfigured);
+    }
+
+    let serialized =
+        std::fs::read_to_string(path).map_err(|e| AcmeError::Read(path.to_string(), e))?;
+    let resolvers: BTreeMap<String, AcmeResolver> = serde_json::from_str(&serialized)?;
+
+    let mut certificates = Vec::new();
+    for (resolver, content) in resolvers {
+        for certificate in content.certificates.unwrap_or_default() {
+            let info = STANDARD
+                .decode(certificate.certificate.trim())
+                .map_err(|e| e.to_string())
+                .and_then(|pem| CertificateInfo::from_pem(&pem));
+
+            let (info, error) = match info {
+                Ok(info) => (Some(info), None),
+                Err(e) => (None, Some(e)),
+            };
+
+            certificates.push(Certificate {
+                resolver: resolver.clone(),
+                main: certificate.domain.main,
+                sans: certificate.domain.sans.unwrap_or_default(),
+                info,
+                error,
+                routes: Vec::new(),
+            });
+        }
+    }
+
+    Ok(certificates)
+}
+
+/// Days left of the longest valid certificate covering every host, the worst case over all hosts
+fn uploaded_days_left(uploaded: &[CertificateInfo], host: &str) -> Option<i64> {
+    host.split(',')
+        .map(str::trim)
+        .map(|host| {
+            uploaded
+                .iter()
+                .filter(|info| info.covers(host))
+                .map(|info| info.days_left)
+                .max()
+        })
+        .try_fold(i64::MAX, |days_left, best| Some(days_left.min(best?)))
+}
+
+/// Links the certificates to the routes and returns the coverage of every route.
+/// Routes without an ACME certificate may be served by an `uploaded` certificate
+/// or may not use a resolver at all, those aren't reported as missing.
+pub fn match_routes(
+    certificates: &mut [Certificate],
+    uploaded: &[CertificateInfo],
+    routes: &[HttpsRoute],
+    default_resolver: &Option<String>,
+) -> Vec<RouteCoverage> {
+    let mut coverage = Vec::new();
+
+    for route in routes {
+        if route.host_regex {
+            coverage.push(RouteCoverage {
+                name: route.name.clone(),
+                host: route.host.clone(),
+                state: "regex",
+                days_left: None,
+            });
+            continue;
+        }
+
+        // the worst case over all hosts, using the longest valid certificate for every host
+        let mut days_left = Some(i64::MAX);
+        for host in route.host.split(',').map(str::trim) {
+            let mut best = None;
+            for certificate in certificates.iter_mut() {
+                let Some(info) = &certificate.info else {
+                    continue;
+                };
+                if info.covers(host) {
+                    best = best.max(Some(info.days_left));
+                    if !certificate.routes.contains(&route.name) {
+                        certificate.routes.push(route.name.clone());
+                    }
+                }
+            }
+            days_left = days_left.min(best);
+        }
+
+        let (state, days_left) = match days_left {
+            Some(days) if days < EXPIRY_WARNING_DAYS => ("expiring", Some(days)),
+            Some(days) => ("ok", Some(days)),
+            None => match uploaded_days_left(uploaded, &route.host) {
+                Some(days) if days < EXPIRY_WARNING_DAYS => ("expiring", Some(days)),
+                Some(days) => ("uploaded", Some(days)),
+                None if route.tls(default_resolver).cert_resolver.is_none() => {
+                    ("no-resolver", None)
+                }
+                None => ("missing", None),
+            },
+        };
+
+        coverage.push(RouteCoverage {
+            name: route.name.clone(),
+            host: route.host.clone(),
+            state,
+            days_left,
+        });
+    }
+
+    coverage
+}
+
+#[derive(Serialize)]
+struct Acme {
+    error: Option<String>,
+    certificates: Vec<Certificate>,
+    routes: Vec<RouteCoverage>,
+    expiry_warning_days: i64,
+}
+
+#[get("/acme")]
+pub async fn index(conn: DbConn, config: &State<ConfigState>) -> Template {
+    let mut certificates = match load(&config.config().acme_json_path) {
+        Ok(certificates) => certificates,
+        Err(e) => {
+            return Template::render(
+                "acme",
+                Acme {
+                    error: Some(e.to_string()),
+                    certificates: Vec::new(),
+                    routes: Vec::new(),
+                    expiry_warning_days: EXPIRY_WARNING_DAYS,
+                },
+            )
+        }
+    };
+
+    let (routes, error) = match HttpsRoute::all(&conn).await {
+        Ok(routes) => (routes, None),
+        Err(e) => {
+            error!("DB error loading HTTPS routes: {}", e);
+            (Vec::new(), Some(e.to_string()))
+        }
+    };
+    let routes = routes
+        .into_iter()
+        .filter(|route| route.enabled)
+        .collect::<Vec<_>>();
+
+    let uploaded = match certificates::Certificate::all(&conn).await {
+        Ok(uploaded) => uploaded
+            .iter()
+            .filter_map(|certificate| {
+                CertificateInfo::from_pem(certificate.cert_pem.as_bytes()).ok()
+            })
+            .collect(),
+        Err(e) => {
+            error!("DB error loading certificates: {}", e);
+            Vec::new()
+        }
+    };
+
+    let config = config.config();
+    let default_resolver =
+        Some(config.acme_provider_name.clone()).filter(|resolver| !resolver.is_empty());
+
+    let routes = match_routes(&mut certificates, &uploaded, &routes, &default_resolver);
+
+    Template::render(
+        "acme",
+        Acme {
+            error,
+            certificates,
+            routes,
+            expiry_warning_days: EXPIRY_WARNING_DAYS,
+        },
+    )
+}
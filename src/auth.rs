@@ -0,0 +1,618 @@
+//! Realms of users for HTTP authentication, e.g. to protect admin interfaces.
+//!
+//! Every realm becomes a `basicAuth` or `digestAuth` middleware with all of its users,
+//! HTTP and HTTPS routes can select one. Passwords are only stored hashed.
+
+use std::collections::BTreeMap;
+
+use diesel::{Connection, ExpressionMethods, QueryDsl, QueryResult, RunQueryDsl};
+use md5::{Digest, Md5};
+use rand::Rng;
+use rocket::{
+    form::Form,
+    request::FlashMessage,
+    response::{Flash, Redirect},
+    State,
+};
+use rocket_dyn_templates::Template;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    config::ConfigState,
+    export_traefik_config,
+    schema::{auth_realms, http_routes, https_routes},
+    traefik::{HttpAuth, HttpMiddleware},
+    DbConn,
+};
+
+pub const ALGORITHMS: [&str; 2] = ["bcrypt", "apr1"];
+
+const APR1_MAGIC: &str = "$apr1$";
+
+/// Alphabet of the crypt base64 encoding, used for salts and the apr1 hash
+const ITOA64: &[u8] = b"./0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+#[derive(Debug, Error)]
+pub enum AuthRealmError {
+    #[error("the realm is used by {0}, select another realm for these routes first")]
+    InUse(String),
+    #[error(transparent)]
+    Db(#[from] diesel::result::Error),
+}
+
+#[derive(Serialize, Deserialize, Queryable, Insertable, AsChangeset, Clone, Debug)]
+#[serde(crate = "rocket::serde")]
+#[diesel(table_name = auth_realms)]
+pub struct AuthRealm {
+    pub id: Option<i32>,
+    pub name: String,
+    /// Use `digestAuth` instead of `basicAuth`
+    pub digest: bool,
+    /// Don't forward the `Authorization` header to the target
+    pub remove_header: bool,
+    /// One `user:hash` per line like htpasswd, `user:realm:hash` for digest realms
+    pub users: String,
+}
+
+fn to64(mut value: u32, length: usize) -> String {
+    (0..length)
+        .map(|_| {
+            let c = ITOA64[(value & 0x3f) as usize] as char;
+            value >>= 6;
+            c
+        })
+        .collect()
+}
+
+/// The MD5 based hash of the Apache htpasswd tool
+fn apr1(password: &str, salt: &str) -> String {
+    let password = password.as_bytes();
+
+    let mut alternate = Md5::new();
+    alternate.update(password);
+    alternate.update(salt);
+    alternate.update(password);
+    let alternate = alternate.finalize();
+
+    let mut context = Md5::new();
+    context.update(password);
+    context.update(APR1_MAGIC);
+    context.update(salt);
+    for chunk in password.chunks(16) {
+        context.update(&alternate[..chunk.len()]);
+    }
+    let mut i = password.len();
+    while i > 0 {
+        if i & 1 == 1 {
+            context.update([0]);
+        } else {
+            context.update(&password[..1]);
+        }
+        i >>= 1;
+    }
+    let mut hash = context.finalize();
+
+    // slows down brute forcing
+    for i in 0..1000 {
+        let mut round = Md5::new();
+        if i & 1 == 1 {
+            round.update(password);
+        } else {
+            round.update(hash);
+        }
+        if i % 3 != 0 {
+            round.update(salt);
+        }
+        if i % 7 != 0 {
+            round.update(password);
+        }
+        if i & 1 == 1 {
+            round.update(hash);
+        } else {
+            round.update(password);
+        }
+        hash = round.finalize();
+    }
+
+    let mut encoded = String::new();
+    for (a, b, c) in [(0, 6, 12), (1, 7, 13), (2, 8, 14), (3, 9, 15), (4, 10, 5)] {
+        let value = ((hash[a] as u32) << 16) | ((hash[b] as u32) << 8) | hash[c] as u32;
+        encoded.push_str(&to64(value, 4));
+    }
+    encoded.push_str(&to64(hash[11] as u32, 2));
+
+    format!("{}{}${}", APR1_MAGIC, salt, encoded)
+}
+
+fn hash_password(password: &str, algorithm: &str) -> Result<String, String> {
+    match algorithm {
+        "bcrypt" => bcrypt::hash(password, bcrypt::DEFAULT_COST).map_err(|e| e.to_string()),
+        "apr1" => {
+            let mut rng = rand::thread_rng();
+            let salt: String = (0..8)
+                .map(|_| ITOA64[rng.gen_range(0..ITOA64.len())] as char)
+                .collect();
+            Ok(apr1(password, &salt))
+        }
+        _ => Err(format!("unknown algorithm {}", algorithm)),
+    }
+}
+
+impl AuthRealm {
+    pub async fn all(conn: &DbConn) -> QueryResult<Vec<AuthRealm>> {
+        conn.run(|c| auth_realms::table.load::<AuthRealm>(c)).await
+    }
+
+    pub async fn get(id: i32, conn: &DbConn) -> QueryResult<AuthRealm> {
+        conn.run(move |c| auth_realms::table.filter(auth_realms::id.eq(id)).first(c))
+            .await
+    }
+
+    pub async fn names(conn: &DbConn) -> QueryResult<Vec<String>> {
+        conn.run(|c| {
+            auth_realms::table
+                .select(auth_realms::name)
+                .order(auth_realms::name)
+                .load::<String>(c)
+        })
+        .await
+    }
+
+    pub async fn insert(mut realm: AuthRealm, conn: &DbConn) -> QueryResult<usize> {
+        realm.cleanup();
+        conn.run(move |c| {
+            diesel::insert_into(auth_realms::table)
+                .values(&realm)
+                .execute(c)
+        })
+        .await
+    }
+
+    /// Updates the realm and the routes using it, in case it was renamed
+    pub async fn update(id: i32, mut realm: AuthRealm, conn: &DbConn) -> QueryResult<usize> {
+        realm.cleanup();
+        conn.run(move |c| {
+            c.transaction(|c| {
+                let name = auth_realms::table
+                    .filter(auth_realms::id.eq(id))
+                    .select(auth_realms::name)
+                    .first::<String>(c)?;
+
+                diesel::update(http_routes::table)
+                    .filter(http_routes::auth_realm.eq(&name))
+                    .set(http_routes::auth_realm.eq(&realm.name))
+                    .execute(c)?;
+                diesel::update(https_routes::table)
+                    .filter(https_routes::auth_realm.eq(&name))
+                    .set(https_routes::auth_realm.eq(&realm.name))
+                    .execute(c)?;
+
+                diesel::update(auth_realms::table)
+                    .filter(auth_realms::id.eq(id))
+                    .set(&realm)
+                    .execute(c)
+            })
+        })
+        .await
+    }
+
+    /// Deletes the realm. Realms used by routes can't be deleted,
+    /// the routes would be published without a login.
+    pub async fn delete(id: i32, conn: &DbConn) -> Result<usize, AuthRealmError> {
+        conn.run(move |c| {
+            c.transaction(|c| {
+                let name = auth_realms::table
+                    .filter(auth_realms::id.eq(id))
+                    .select(auth_realms::name)
+                    .first::<String>(c)?;
+
+                let http = http_routes::table
+                    .filter(http_routes::auth_realm.eq(&name))
+                    .select(http_routes::name)
+                    .load::<String>(c)?;
+                let https = https_routes::table
+                    .filter(https_routes::auth_realm.eq(&name))
+                    .select(https_routes::name)
+                    .load::<String>(c)?;
+
+                let routes = http
+                    .iter()
+                    .map(|route| format!("HTTP route {}", route))
+                    .chain(https.iter().map(|route| format!("HTTPS route {}", route)))
+                    .collect::<Vec<_>>();
+                if !routes.is_empty() {
+                    return Err(AuthRealmError::InUse(routes.join(", ")));
+                }
+
+                Ok(diesel::delete(auth_realms::table)
+                    .filter(auth_realms::id.eq(id))
+                    .execute(c)?)
+            })
+        })
+        .await
+    }
+
+    pub fn cleanup(&mut self) {
+        self.name = self.name.trim().to_string();
+        self.users = self
+            .users
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n");
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.is_empty() {
+            return Err("a realm needs a name".into());
+        }
+
+        // the realm is part of the users of digest realms
+        if self.digest && self.name.contains(':') {
+            return Err("the name of a digest realm can't contain :".into());
+        }
+
+        let fields = if self.digest { 3 } else { 2 };
+        for line in self.users.lines() {
+            if line.split(':').count() != fields {
+                return Err(format!("invalid user {}", line));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Usernames and how their passwords are hashed
+    pub fn usernames(&self) -> Vec<(String, &'static str)> {
+        self.users
+            .lines()
+            .filter_map(|line| {
+                let (username, hash) = line.split_once(':')?;
+                let algorithm = if self.digest {
+                    "md5"
+                } else if hash.starts_with(APR1_MAGIC) {
+                    "apr1"
+                } else if hash.starts_with("$2") {
+                    "bcrypt"
+                } else {
+                    "other"
+                };
+                Some((username.to_string(), algorithm))
+            })
+            .collect()
+    }
+
+    /// Adds the user or changes the password of an existing one
+    pub fn set_user(
+        &mut self,
+        username: &str,
+        password: &str,
+        algorithm: &str,
+    ) -> Result<(), String> {
+        let username = username.trim();
+        if username.is_empty() || username.contains(':') {
+            return Err("the username can't be empty or contain :".into());
+        }
+        if password.is_empty() {
+            return Err("the password can't be empty".into());
+        }
+
+        // digest auth needs the hash of the username, realm and password, so the algorithm is fixed
+        let line = if self.digest {
+            let hash = Md5::digest(format!("{}:{}:{}", username, self.name, password));
+            let hash: String = hash.iter().map(|byte| format!("{:02x}", byte)).collect();
+            format!("{}:{}:{}", username, self.name, hash)
+        } else {
+            format!("{}:{}", username, hash_password(password, algorithm)?)
+        };
+
+        self.remove_user(username);
+        self.users = self
+            .users
+            .lines()
+            .chain([line.as_str()])
+            .collect::<Vec<_>>()
+            .join("\n");
+        Ok(())
+    }
+
+    pub fn remove_user(&mut self, username: &str) {
+        self.users = self
+            .users
+            .lines()
+            .filter(|line| line.split(':').next() != Some(username))
+            .collect::<Vec<_>>()
+            .join("\n");
+    }
+
+    pub fn middleware_name(&self) -> String {
+        format!("gui-auth-{}-{}", self.id.unwrap_or_default(), self.name)
+    }
+
+    fn middleware(&self) -> HttpMiddleware {
+        let auth = HttpAuth {
+            users: self.users.lines().map(String::from).collect(),
+            realm: self.name.clone(),
+            remove_header: self.remove_header,
+        };
+
+        if self.digest {
+            HttpMiddleware {
+                digest_auth: Some(auth),
+                ..Default::default()
+            }
+        } else {
+            HttpMiddleware {
+                basic_auth: Some(auth),
+                ..Default::default()
+            }
+        }
+    }
+
+    pub async fn generate_traefik_config(conn: &DbConn) -> BTreeMap<String, HttpMiddleware> {
+        let realms = AuthRealm::all(conn).await.unwrap();
+
+        realms
+            .iter()
+            .map(|realm| (realm.middleware_name(), realm.middleware()))
+            .collect()
+    }
+}
+
+/// Middleware of the realm selected by a route.
+/// An unknown realm still gets a middleware reference, so traefik fails the route instead of exposing it.
+pub fn route_middleware(realms: &[AuthRealm], selected: Option<&str>) -> Option<String> {
+    let selected = selected?;
+    Some(
+        realms
+            .iter()
+            .find(|realm| realm.name == selected)
+            .map(AuthRealm::middleware_name)
+            .unwrap_or_else(|| format!("gui-auth-missing-{}", selected)),
+    )
+}
+
+#[derive(Serialize)]
+struct RealmView {
+    realm: AuthRealm,
+    usernames: Vec<(String, &'static str)>,
+}
+
+#[derive(Serialize)]
+struct AuthRealms {
+    flash: Option<(String, String)>,
+    realms: Vec<RealmView>,
+    edit: Option<i32>,
+    algorithms: [&'static str; 2],
+}
+
+impl AuthRealms {
+    pub async fn raw(conn: &DbConn, flash: Option<(String, String)>, edit: Option<i32>) -> Self {
+        let (realms, flash) = match AuthRealm::all(conn).await {
+            Ok(realms) => (realms, flash),
+            Err(e) => {
+                error!("DB error loading auth realms: {}", e);
+                (Vec::new(), Some(("error".into(), e.to_string())))
+            }
+        };
+
+        Self {
+            flash,
+            realms: realms
+                .into_iter()
+                .map(|realm| RealmView {
+                    usernames: realm.usernames(),
+                    realm,
+                })
+                .collect(),
+            edit,
+            algorithms: ALGORITHMS,
+        }
+    }
+}
+
+#[derive(FromForm)]
+pub struct RealmForm {
+    name: String,
+    digest: bool,
+    remove_header: bool,
+}
+
+#[derive(FromForm)]
+pub struct UserForm {
+    username: String,
+    password: String,
+    algorithm: String,
+}
+
+#[get("/auth?<edit>")]
+pub async fn index(edit: Option<i32>, flash: Option<FlashMessage<'_>>, conn: DbConn) -> Template {
+    let flash = flash.map(FlashMessage::into_inner);
+    Template::render("auth", AuthRealms::raw(&conn, flash, edit).await)
+}
+
+#[post("/auth", data = "<realm_form>")]
+pub async fn create(
+    realm_form: Form<RealmForm>,
+    conn: DbConn,
+    config: &State<ConfigState>,
+) -> Flash<Redirect> {
+    let realm_form = realm_form.into_inner();
+    let mut realm = AuthRealm {
+        id: None,
+        name: realm_form.name,
+        digest: realm_form.digest,
+        remove_header: realm_form.remove_header,
+        users: String::new(),
+    };
+    realm.cleanup();
+
+    if let Err(e) = realm.validate() {
+        return Flash::error(Redirect::to("/auth"), e);
+    }
+
+    if let Err(e) = AuthRealm::insert(realm, &conn).await {
+        Flash::error(Redirect::to("/auth"), e.to_string())
+    } else {
+        export_traefik_config(&conn, &config.config()).await;
+        Flash::success(Redirect::to("/auth"), "Realm created")
+    }
+}
+
+#[post("/auth/<id>", data = "<realm_form>")]
+pub async fn update(
+    id: i32,
+    realm_form: Form<RealmForm>,
+    conn: DbConn,
+    config: &State<ConfigState>,
+) -> Flash<Redirect> {
+    let mut realm = match AuthRealm::get(id, &conn).await {
+        Ok(realm) => realm,
+        Err(e) => return Flash::error(Redirect::to("/auth"), e.to_string()),
+    };
+
+    let realm_form = realm_form.into_inner();
+    let hashes_changed =
+        realm.digest != realm_form.digest || (realm.digest && realm.name != realm_form.name.trim());
+    if hashes_changed && !realm.users.is_empty() {
+        return Flash::error(
+            Redirect::to(format!("/auth?edit={}", id)),
+            "The passwords of digest realms depend on the name, remove the users first",
+        );
+    }
+
+    realm.name = realm_form.name;
+    realm.digest = realm_form.digest;
+    realm.remove_header = realm_form.remove_header;
+    realm.cleanup();
+
+    if let Err(e) = realm.validate() {
+        return Flash::error(Redirect::to(format!("/auth?edit={}", id)), e);
+    }
+
+    if let Err(e) = AuthRealm::update(id, realm, &conn).await {
+        Flash::error(Redirect::to("/auth"), e.to_string())
+    } else {
+        export_traefik_config(&conn, &config.config()).await;
+        Flash::success(Redirect::to("/auth"), "Realm updated")
+    }
+}
+
+#[post("/auth/<id>/delete", data = "<confirm>")]
+pub async fn delete(
+    id: i32,
+    confirm: Form<bool>,
+    conn: DbConn,
+    config: &State<ConfigState>,
+) -> Flash<Redirect> {
+    if confirm.into_inner() {
+        if let Err(e) = AuthRealm::delete(id, &conn).await {
+            Flash::error(Redirect::to("/auth"), e.to_string())
+        } else {
+            export_traefik_config(&conn, &config.config()).await;
+            Flash::success(Redirect::to("/auth"), "Realm deleted")
+        }
+    } else {
+        Flash::error(Redirect::to("/auth"), "Delete cancelled")
+    }
+}
+
+#[post("/auth/<id>/users", data = "<user_form>")]
+pub async fn set_user(
+    id: i32,
+    user_form: Form<UserForm>,
+    conn: DbConn,
+    config: &State<ConfigState>,
+) -> Flash<Redirect> {
+    let mut realm = match AuthRealm::get(id, &conn).await {
+        Ok(realm) => realm,
+        Err(e) => return Flash::error(Redirect::to("/auth"), e.to_string()),
+    };
+
+    if let Err(e) = realm.set_user(
+        &user_form.username,
+        &user_form.password,
+        &user_form.algorithm,
+    ) {
+        return Flash::error(Redirect::to("/auth"), e);
+    }
+
+    if let Err(e) = AuthRealm::update(id, realm, &conn).await {
+        Flash::error(Redirect::to("/auth"), e.to_string())
+    } else {
+        export_traefik_config(&conn, &config.config()).await;
+        Flash::success(Redirect::to("/auth"), "User saved")
+    }
+}
+
+#[post("/auth/<id>/users/<username>/delete", data = "<confirm>")]
+pub async fn delete_user(
+    id: i32,
+    username: &str,
+    confirm: Form<bool>,
+    conn: DbConn,
+    config: &State<ConfigState>,
+) -> Flash<Redirect> {
+    if !confirm.into_inner() {
+        return Flash::error(Redirect::to("/auth"), "Delete cancelled");
+    }
+
+    let mut realm = match AuthRealm::get(id, &conn).await {
+        Ok(realm) => realm,
+        Err(e) => return Flash::error(Redirect::to("/auth"), e.to_string()),
+    };
+    realm.remove_user(username);
+
+    if let Err(e) = AuthRealm::update(id, realm, &conn).await {
+        Flash::error(Redirect::to("/auth"), e.to_string())
+    } else {
+        export_traefik_config(&conn, &config.config()).await;
+        Flash::success(Redirect::to("/auth"), "User deleted")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Vectors from `openssl passwd -apr1 -salt <salt> <password>`
+    #[test]
+    fn apr1_matches_openssl() {
+        assert_eq!(
+            apr1("p@ss w0rd!", "abcdefgh"),
+            "$apr1$abcdefgh$Zx5npvb9OfDIre7tJqMfC0"
+        );
+        assert_eq!(
+            apr1("password", "12345678"),
+            "$apr1$12345678$9pHAGSBYtlmFtid2xxNog0"
+        );
+        assert_eq!(apr1("secret", "xy"), "$apr1$xy$94JxxwMvxqQwA3Iid6OL9.");
+        assert_eq!(
+            apr1("a much longer password over sixteen bytes", "Qw3rTy12"),
+            "$apr1$Qw3rTy12$i0eesfVLUBhq14s2XgaAL."
+        );
+    }
+
+    #[test]
+    fn unknown_realm_keeps_a_middleware() {
+        let realms = vec![AuthRealm {
+            id: Some(1),
+            name: "admins".into(),
+            digest: false,
+            remove_header: false,
+            users: String::new(),
+        }];
+
+        assert_eq!(
+            route_middleware(&realms, Some("admins")),
+            Some("gui-auth-1-admins".into())
+        );
+        assert_eq!(
+            route_middleware(&realms, Some("deleted")),
+            Some("gui-auth-missing-deleted".into())
+        );
+        assert_eq!(route_middleware(&realms, None), None);
+    }
+}
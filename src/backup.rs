@@ -0,0 +1,410 @@
+use diesel::{Connection, QueryDsl, QueryResult, RunQueryDsl};
+use rocket::{
+    form::Form,
+    fs::TempFile,
+    http::ContentType,
+    response::{Flash, Redirect},
+    tokio::io::AsyncReadExt,
+    State,
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    auth::AuthRealm,
+    certificates::Certificate,
+    config::{Config, ConfigError, ConfigState},
+    error_pages::ErrorPage,
+    export_traefik_config,
+    headers::HeaderProfile,
+    http::HttpRoute,
+    https::HttpsRoute,
+    redirect::RedirectRoute,
+    schema::{
+        auth_realms, certificates, error_pages, header_profiles, http_routes, https_routes,
+        redirect_routes, route_checks, tls_options, tls_routes,
+    },
+    tls::TlsRoute,
+    tls_options::TlsOption,
+    DbConn, Download,
+};
+
+/// Version of the backup document format.
+/// Bump this whenever a change can't be read by older versions.
+pub const BACKUP_VERSION: u32 = 1;
+
+#[derive(Debug, Error)]
+pub enum BackupError {
+    #[error("error reading backup file: {0}")]
+    Read(#[from] std::io::Error),
+    #[error("error parsing backup file: {0}")]
+    Parse(#[from] serde_yaml::Error),
+    #[error("unsupported backup version {0}, this version supports up to {BACKUP_VERSION}")]
+    Version(u32),
+    #[error("invalid backup: {0}")]
+    Invalid(String),
+    #[error("error restoring routes: {0}")]
+    Database(#[from] diesel::result::Error),
+    #[error("the backup conflicts with existing entries, rename or delete them first: {0}")]
+    Conflict(String),
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Backup {
+    pub version: u32,
+    pub config: Config,
+    #[serde(default)]
+    pub http_routes: Vec<HttpRoute>,
+    #[serde(default)]
+    pub https_routes: Vec<HttpsRoute>,
+    #[serde(default)]
+    pub tls_routes: Vec<TlsRoute>,
+    #[serde(default)]
+    pub redirect_routes: Vec<RedirectRoute>,
+    #[serde(default)]
+    pub certificates: Vec<Certificate>,
+    #[serde(default)]
+    pub tls_options: Vec<TlsOption>,
+    #[serde(default)]
+    pub error_pages: Vec<ErrorPage>,
+    #[serde(default)]
+    pub header_profiles: Vec<HeaderProfile>,
+    #[serde(default)]
+    pub auth_realms: Vec<AuthRealm>,
+}
+
+#[derive(FromFormField, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RestoreMode {
+    /// add the routes of the backup to the existing ones and keep the current config
+    Merge,
+    /// drop all existing routes and replace them and the config with the backup
+    Replace,
+}
+
+impl Backup {
+    pub async fn create(conn: &DbConn, config: Config) -> QueryResult<Self> {
+        Ok(Self {
+            version: BACKUP_VERSION,
+            config,
+            http_routes: HttpRoute::all(conn).await?,
+            https_routes: HttpsRoute::all(conn).await?,
+            tls_routes: TlsRoute::all(conn).await?,
+            redirect_routes: RedirectRoute::all(conn).await?,
+            certificates: Certificate::all(conn).await?,
+            tls_options: TlsOption::all(conn).await?,
+            error_pages: ErrorPage::all(conn).await?,
+            header_profiles: HeaderProfile::all(conn).await?,
+            auth_realms: AuthRealm::all(conn).await?,
+        })
+    }
+
+    pub fn parse(serialized: &str) -> Result<Self, BackupError> {
+        // JSON is valid YAML, so this handles both export formats
+        let backup: Backup = serde_yaml::from_str(serialized)?;
+        backup.validate()?;
+        Ok(backup)
+    }
+
+    pub fn validate(&self) -> Result<(), BackupError> {
+        if self.version == 0 || self.version > BACKUP_VERSION {
+            return Err(BackupError::Version(self.version));
+        }
+
+        let routes = self
+            .http_routes
+            .iter()
+            .map(|r| ("HTTP", r.id, &r.name, &r.host, &r.target))
+            .chain(
+                self.https_routes
+                    .iter()
+                    .map(|r| ("HTTPS", r.id, &r.name, &r.host, &r.target)),
+            )
+            .chain(
+                self.tls_routes
+                    .iter()
+                    .map(|r| ("TLS", r.id, &r.name, &r.host, &r.target)),
+            )
+            .chain(
+                self.redirect_routes
+                    .iter()
+                    .map(|r| ("Redirect", r.id, &r.name, &r.host, &r.destination)),
+            );
+
+        for (kind, id, name, host, target) in routes {
+            let id = id.map(|id| id.to_string()).unwrap_or_else(|| "-".into());
+            if name.trim().is_empty() {
+                return Err(BackupError::Invalid(format!(
+                    "{} route {} has no name",
+                    kind, id
+                )));
+            }
+            if host.trim().is_empty() {
+                return Err(BackupError::Invalid(format!(
+                    "{} route {} ({}) has no host",
+                    kind, id, name
+                )));
+            }
+            if target.trim().is_empty() {
+                return Err(BackupError::Invalid(format!(
+                    "{} route {} ({}) has no target",
+                    kind, id, name
+                )));
+            }
+        }
+
+        for route in &self.redirect_routes {
+            route.validate().map_err(|e| {
+                BackupError::Invalid(format!("redirect route {}: {}", route.name, e))
+            })?;
+        }
+
+        for realm in &self.auth_realms {
+            realm
+                .validate()
+                .map_err(|e| BackupError::Invalid(format!("realm {}: {}", realm.name, e)))?;
+        }
+
+        for profile in &self.header_profiles {
+            profile.validate().map_err(|e| {
+                BackupError::Invalid(format!("header profile {}: {}", profile.name, e))
+            })?;
+        }
+
+        for page in &self.error_pages {
+            page.validate()
+                .map_err(|e| BackupError::Invalid(format!("error page {}: {}", page.name, e)))?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn restore(
+        self,
+        mode: RestoreMode,
+        conn: &DbConn,
+        state: &ConfigState,
+    ) -> Result<(), BackupError> {
+        let Backup {
+            mut config,
+            mut http_routes,
+            mut https_routes,
+            mut tls_routes,
+            mut redirect_routes,
+            mut certificates,
+            mut tls_options,
+            mut error_pages,
+            mut header_profiles,
+            mut auth_realms,
+            ..
+        } = self;
+
+        if mode == RestoreMode::Merge {
+            // let the database assign new ids so nothing collides with existing routes
+            http_routes.iter_mut().for_each(|r| r.id = None);
+            https_routes.iter_mut().for_each(|r| r.id = None);
+            tls_routes.iter_mut().for_each(|r| r.id = None);
+            redirect_routes.iter_mut().for_each(|r| r.id = None);
+            certificates.iter_mut().for_each(|c| c.id = None);
+            // keep the current default certificate
+            certificates
+                .iter_mut()
+                .for_each(|c| c.default_certificate = false);
+            tls_options.iter_mut().for_each(|o| o.id = None);
+            error_pages.iter_mut().for_each(|p| p.id = None);
+            header_profiles.iter_mut().for_each(|p| p.id = None);
+            auth_realms.iter_mut().for_each(|r| r.id = None);
+        }
+
+        http_routes.iter_mut().for_each(HttpRoute::cleanup);
+        https_routes.iter_mut().for_each(HttpsRoute::cleanup);
+        tls_routes.iter_mut().for_each(TlsRoute::cleanup);
+        redirect_routes.iter_mut().for_each(RedirectRoute::cleanup);
+        tls_options.iter_mut().for_each(TlsOption::cleanup);
+        error_pages.iter_mut().for_each(ErrorPage::cleanup);
+        header_profiles.iter_mut().for_each(HeaderProfile::cleanup);
+        auth_realms.iter_mut().for_each(AuthRealm::cleanup);
+
+        // serialized up front, a failing config can't leave the routes replaced
+        config.cleanup();
+        let serialized = serde_yaml::to_string(&config).map_err(ConfigError::from)?;
+
+        conn.run(move |c| {
+            c.transaction(|c| {
+                if mode == RestoreMode::Merge {
+                    // routes reference these by name, keeping the local ones would silently
+                    // change e.g. the users or client CAs of the merged routes
+                    let mut conflicts = Vec::new();
+                    let existing = tls_options::table
+                        .select(tls_options::name)
+                        .load::<String>(c)?;
+                    conflicts.extend(
+                        tls_options
+                            .iter()
+                            .filter(|o| existing.contains(&o.name))
+                            .map(|o| format!("TLS options {}", o.name)),
+                    );
+                    let existing = error_pages::table
+                        .select(error_pages::name)
+                        .load::<String>(c)?;
+                    conflicts.extend(
+                        error_pages
+                            .iter()
+                            .filter(|p| existing.contains(&p.name))
+                            .map(|p| format!("error page {}", p.name)),
+                    );
+                    let existing = header_profiles::table
+                        .select(header_profiles::name)
+                        .load::<String>(c)?;
+                    conflicts.extend(
+                        header_profiles
+                            .iter()
+                            .filter(|p| existing.contains(&p.name))
+                            .map(|p| format!("header profile {}", p.name)),
+                    );
+                    let existing = auth_realms::table
+                        .select(auth_realms::name)
+                        .load::<String>(c)?;
+                    conflicts.extend(
+                        auth_realms
+                            .iter()
+                            .filter(|r| existing.contains(&r.name))
+                            .map(|r| format!("realm {}", r.name)),
+                    );
+                    if !conflicts.is_empty() {
+                        return Err(BackupError::Conflict(conflicts.join(", ")));
+                    }
+                }
+
+                if mode == RestoreMode::Replace {
+                    // the ids of the restored routes would pick up the old results
+                    diesel::delete(route_checks::table).execute(c)?;
+                    diesel::delete(http_routes::table).execute(c)?;
+                    diesel::delete(https_routes::table).execute(c)?;
+                    diesel::delete(tls_routes::table).execute(c)?;
+                    diesel::delete(redirect_routes::table).execute(c)?;
+                    diesel::delete(certificates::table).execute(c)?;
+                    diesel::delete(tls_options::table).execute(c)?;
+                    diesel::delete(error_pages::table).execute(c)?;
+                    diesel::delete(header_profiles::table).execute(c)?;
+                    diesel::delete(auth_realms::table).execute(c)?;
+                }
+
+                for route in &http_routes {
+                    diesel::insert_into(http_routes::table)
+                        .values(route)
+                        .execute(c)?;
+                }
+                for route in &https_routes {
+                    diesel::insert_into(https_routes::table)
+                        .values(route)
+                        .execute(c)?;
+                }
+                for route in &tls_routes {
+                    diesel::insert_into(tls_routes::table)
+                        .values(route)
+                        .execute(c)?;
+                }
+                for route in &redirect_routes {
+                    diesel::insert_into(redirect_routes::table)
+                        .values(route)
+                        .execute(c)?;
+                }
+                for certificate in &certificates {
+                    diesel::insert_into(certificates::table)
+                        .values(certificate)
+                        .execute(c)?;
+                }
+                for option in &tls_options {
+                    diesel::insert_into(tls_options::table)
+                        .values(option)
+                        .execute(c)?;
+                }
+                for page in &error_pages {
+                    diesel::insert_into(error_pages::table)
+                        .values(page)
+                        .execute(c)?;
+                }
+                for profile in &header_profiles {
+                    diesel::insert_into(header_profiles::table)
+                        .values(profile)
+                        .execute(c)?;
+                }
+                for realm in &auth_realms {
+                    diesel::insert_into(auth_realms::table)
+                        .values(realm)
+                        .execute(c)?;
+                }
+
+                Ok::<_, BackupError>(())
+            })
+        })
+        .await?;
+
+        if mode == RestoreMode::Replace {
+            state.write(config, &serialized)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(FromForm)]
+pub struct RestoreForm<'r> {
+    file: TempFile<'r>,
+    mode: RestoreMode,
+}
+
+async fn read_backup(file: &TempFile<'_>) -> Result<Backup, BackupError> {
+    let mut serialized = String::new();
+    file.open().await?.read_to_string(&mut serialized).await?;
+    Backup::parse(&serialized)
+}
+
+#[get("/backup?<format>")]
+pub async fn export(
+    format: Option<&str>,
+    conn: DbConn,
+    state: &State<ConfigState>,
+) -> Result<Download, String> {
+    let backup = Backup::create(&conn, state.config())
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if format == Some("json") {
+        let serialized = serde_json::to_string_pretty(&backup).map_err(|e| e.to_string())?;
+        Ok(Download::new(
+            ContentType::JSON,
+            "traefik-gui-backup.json",
+            serialized,
+        ))
+    } else {
+        let serialized = serde_yaml::to_string(&backup).map_err(|e| e.to_string())?;
+        Ok(Download::new(
+            ContentType::new("application", "yaml"),
+            "traefik-gui-backup.yaml",
+            serialized,
+        ))
+    }
+}
+
+#[post("/backup", data = "<restore_form>")]
+pub async fn restore(
+    restore_form: Form<RestoreForm<'_>>,
+    conn: DbConn,
+    state: &State<ConfigState>,
+) -> Flash<Redirect> {
+    let backup = match read_backup(&restore_form.file).await {
+        Ok(backup) => backup,
+        Err(e) => return Flash::error(Redirect::to("/config"), e.to_string()),
+    };
+
+    if let Err(e) = backup.restore(restore_form.mode, &conn, state).await {
+        error!("Error restoring backup: {}", e);
+        return Flash::error(Redirect::to("/config"), e.to_string());
+    }
+
+    export_traefik_config(&conn, &state.config()).await;
+    Flash::success(Redirect::to("/config"), "Backup restored")
+}
@@ -0,0 +1,348 @@
+//! Static certificates, e.g. from a private CA, uploaded as PEM files.
+//!
+//! The certificates are stored in the database and written to [`CERT_DIR`] on every export,
+//! so traefik can load them from the shared volume.
+
+use std::path::Path;
+
+use diesel::{Connection, ExpressionMethods, QueryResult, RunQueryDsl};
+use rocket::{
+    form::Form,
+    fs::TempFile,
+    request::FlashMessage,
+    response::{Flash, Redirect},
+    tokio::io::AsyncReadExt,
+    State,
+};
+use rocket_dyn_templates::Template;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::{Config, ConfigState},
+    export_traefik_config, output,
+    schema::certificates::{self, dsl},
+    traefik::{TlsCertificate, TlsConfig, TlsStore},
+    x509::{self, CertificateInfo},
+    DbConn,
+};
+
+const CERT_DIR: &str = "./traefik/certs";
+
+#[derive(Serialize, Deserialize, Queryable, Insertable, AsChangeset, Clone, Debug)]
+#[diesel(table_name = certificates)]
+pub struct Certificate {
+    pub id: Option<i32>,
+    pub name: String,
+    pub cert_pem: String,
+    pub key_pem: String,
+    /// Served by traefik for connections without a matching certificate
+    pub default_certificate: bool,
+}
+
+impl Certificate {
+    pub async fn all(conn: &DbConn) -> QueryResult<Vec<Certificate>> {
+        conn.run(|c| certificates::table.load::<Certificate>(c))
+            .await
+    }
+
+    pub async fn insert(certificate: Certificate, conn: &DbConn) -> QueryResult<usize> {
+        conn.run(move |c| {
+            c.transaction(|c| {
+                if certificate.default_certificate {
+                    diesel::update(certificates::table)
+                        .set(dsl::default_certificate.eq(false))
+                        .execute(c)?;
+                }
+                diesel::insert_into(certificates::table)
+                    .values(&certificate)
+                    .execute(c)
+            })
+        })
+        .await
+    }
+
+    pub async fn delete(id: i32, conn: &DbConn) -> QueryResult<usize> {
+        conn.run(move |c| {
+            diesel::delete(certificates::table)
+                .filter(dsl::id.eq(id))
+                .execute(c)
+        })
+        .await
+    }
+
+    /// Makes the certificate the default one, or removes the default if `id` is `None`
+    pub async fn set_default(id: Option<i32>, conn: &DbConn) -> QueryResult<usize> {
+        conn.run(move |c| {
+            c.transaction(|c| {
+                diesel::update(certificates::table)
+                    .set(dsl::default_certificate.eq(false))
+                    .execute(c)?;
+                match id {
+                    Some(id) => diesel::update(certificates::table)
+                        .filter(dsl::id.eq(id))
+                        .set(dsl::default_certificate.eq(true))
+                        .execute(c),
+                    None => Ok(0),
+                }
+            })
+        })
+        .await
+    }
+
+    fn file_name(&self) -> String {
+        output::file_name(&format!(
+            "gui-cert-{}-{}",
+            self.id.unwrap_or_default(),
+            self.name
+        ))
+    }
+
+    fn traefik_certificate(&self, config: &Config) -> TlsCertificate {
+        TlsCertificate {
+            name: self.file_name(),
+            cert_file: traefik_file(config, &format!("{}.crt", self.file_name()), &self.cert_pem),
+            key_file: traefik_file(config, &format!("{}.key", self.file_name()), &self.key_pem),
+        }
+    }
+
+    pub async fn generate_traefik_config(conn: &DbConn, config: &Config) -> TlsConfig {
+        let mut tls = TlsConfig::new();
+
+        let certificates = Certificate::all(conn).await.unwrap();
+
+        for certificate in certificates {
+            let traefik_certificate = certificate.traefik_certificate(config);
+
+            if certificate.default_certificate {
+                tls.stores.insert(
+                    "default".into(),
+                    TlsStore {
+                        default_certificate: Some(traefik_certificate.clone()),
+                    },
+                );
+            }
+
+            tls.certificates.push(traefik_certificate);
+        }
+
+        tls
+    }
+}
+
+/// A file as traefik loads it, either from the cert path or inline
+pub fn traefik_file(config: &Config, file_name: &str, content: &str) -> String {
+    if config.cert_path.is_empty() {
+        content.to_string()
+    } else {
+        format!("{}/{}", config.cert_path.trim_end_matches('/'), file_name)
+    }
+}
+
+/// A file in the cert directory
+pub struct CertFile {
+    pub name: String,
+    pub content: String,
+    /// only readable by the owner, for private keys
+    pub private: bool,
+}
+
+/// Writes the files starting with `prefix` and removes the ones no longer generated
+pub fn write_cert_files(prefix: &str, files: &[CertFile]) -> std::io::Result<()> {
+    let dir = Path::new(CERT_DIR);
+    std::fs::create_dir_all(dir)?;
+
+    for file in files {
+        std::fs::write(dir.join(&file.name), &file.content)?;
+        #[cfg(unix)]
+        if file.private {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(dir.join(&file.name), std::fs::Permissions::from_mode(0o600))?;
+        }
+    }
+
+    for entry in std::fs::read_dir(dir)? {
+        let file_name = entry?.file_name().to_string_lossy().to_string();
+
+        let generated = file_name.starts_with(prefix)
+            && (file_name.ends_with(".crt") || file_name.ends_with(".key"));
+
+        if generated && !files.iter().any(|file| file.name == file_name) {
+            std::fs::remove_file(dir.join(&file_name))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes the certificate files and removes the files of deleted certificates
+pub async fn write_files(conn: &DbConn) -> std::io::Result<()> {
+    let certificates = Certificate::all(conn)
+        .await
+        .map_err(std::io::Error::other)?;
+
+    let files = certificates
+        .into_iter()
+        .flat_map(|certificate| {
+            [
+                CertFile {
+                    name: format!("{}.crt", certificate.file_name()),
+                    content: certificate.cert_pem.clone(),
+                    private: false,
+                },
+                CertFile {
+                    name: format!("{}.key", certificate.file_name()),
+                    content: certificate.key_pem,
+                    private: true,
+                },
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    write_cert_files("gui-cert-", &files)
+}
+
+#[derive(Serialize)]
+struct CertificateView {
+    id: Option<i32>,
+    name: String,
+    default_certificate: bool,
+    info: Option<CertificateInfo>,
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Certificates {
+    flash: Option<(String, String)>,
+    certificates: Vec<CertificateView>,
+}
+
+impl Certificates {
+    pub async fn raw(conn: &DbConn, flash: Option<(String, String)>) -> Self {
+        match Certificate::all(conn).await {
+            Ok(certificates) => Self {
+                flash,
+                certificates: certificates
+                    .into_iter()
+                    .map(|certificate| {
+                        let (info, error) =
+                            match CertificateInfo::from_pem(certificate.cert_pem.as_bytes()) {
+                                Ok(info) => (Some(info), None),
+                                Err(e) => (None, Some(e)),
+                            };
+                        CertificateView {
+                            id: certificate.id,
+                            name: certificate.name,
+                            default_certificate: certificate.default_certificate,
+                            info,
+                            error,
+                        }
+                    })
+                    .collect(),
+            },
+            Err(e) => {
+                error!("DB error loading certificates: {}", e);
+                Self {
+                    flash: Some(("error".into(), e.to_string())),
+                    certificates: Vec::new(),
+                }
+            }
+        }
+    }
+}
+
+#[derive(FromForm)]
+pub struct CertificateForm<'r> {
+    name: String,
+    cert: TempFile<'r>,
+    key: TempFile<'r>,
+    default_certificate: bool,
+}
+
+async fn read_pem(file: &TempFile<'_>) -> std::io::Result<String> {
+    let mut pem = String::new();
+    file.open().await?.read_to_string(&mut pem).await?;
+    Ok(pem)
+}
+
+#[get("/certificates")]
+pub async fn index(flash: Option<FlashMessage<'_>>, conn: DbConn) -> Template {
+    let flash = flash.map(FlashMessage::into_inner);
+    Template::render("certificates", Certificates::raw(&conn, flash).await)
+}
+
+#[post("/certificates", data = "<certificate_form>")]
+pub async fn create(
+    certificate_form: Form<CertificateForm<'_>>,
+    conn: DbConn,
+    config: &State<ConfigState>,
+) -> Flash<Redirect> {
+    if certificate_form.name.trim().is_empty() {
+        return Flash::error(Redirect::to("/certificates"), "the name can't be empty");
+    }
+
+    let (cert_pem, key_pem) = match (
+        read_pem(&certificate_form.cert).await,
+        read_pem(&certificate_form.key).await,
+    ) {
+        (Ok(cert_pem), Ok(key_pem)) => (cert_pem, key_pem),
+        (Err(e), _) | (_, Err(e)) => {
+            return Flash::error(Redirect::to("/certificates"), e.to_string())
+        }
+    };
+
+    if let Err(e) = CertificateInfo::from_pem(cert_pem.as_bytes())
+        .and_then(|_| x509::key_matches(cert_pem.as_bytes(), key_pem.as_bytes()))
+    {
+        return Flash::error(Redirect::to("/certificates"), e);
+    }
+
+    let certificate = Certificate {
+        id: None,
+        name: certificate_form.name.trim().to_string(),
+        cert_pem,
+        key_pem,
+        default_certificate: certificate_form.default_certificate,
+    };
+
+    if let Err(e) = Certificate::insert(certificate, &conn).await {
+        Flash::error(Redirect::to("/certificates"), e.to_string())
+    } else {
+        export_traefik_config(&conn, &config.config()).await;
+        Flash::success(Redirect::to("/certificates"), "Certificate uploaded")
+    }
+}
+
+#[post("/certificates/<id>/default", data = "<default>")]
+pub async fn set_default(
+    id: i32,
+    default: Form<bool>,
+    conn: DbConn,
+    config: &State<ConfigState>,
+) -> Flash<Redirect> {
+    let id = default.into_inner().then_some(id);
+    if let Err(e) = Certificate::set_default(id, &conn).await {
+        Flash::error(Redirect::to("/certificates"), e.to_string())
+    } else {
+        export_traefik_config(&conn, &config.config()).await;
+        Flash::success(Redirect::to("/certificates"), "Default certificate updated")
+    }
+}
+
+#[post("/certificates/<id>/delete", data = "<confirm>")]
+pub async fn delete(
+    id: i32,
+    confirm: Form<bool>,
+    conn: DbConn,
+    config: &State<ConfigState>,
+) -> Flash<Redirect> {
+    if confirm.into_inner() {
+        if let Err(e) = Certificate::delete(id, &conn).await {
+            Flash::error(Redirect::to("/certificates"), e.to_string())
+        } else {
+            export_traefik_config(&conn, &config.config()).await;
+            Flash::success(Redirect::to("/certificates"), "Certificate deleted")
+        }
+    } else {
+        Flash::error(Redirect::to("/certificates"), "Delete cancelled")
+    }
+}
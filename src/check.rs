@@ -0,0 +1,278 @@
+//! Reachability checks of the route targets.
+//!
+//! HTTP and HTTPS routes are probed with a GET request, TLS routes with a TCP connect.
+//! The last result of every route is stored, so the route tables can show it.
+
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use diesel::{upsert::excluded, ExpressionMethods, QueryDsl, QueryResult, RunQueryDsl};
+use futures::{stream, StreamExt};
+use rocket::{
+    response::{Flash, Redirect},
+    tokio::{net::TcpStream, time::timeout},
+    Orbit, Rocket,
+};
+use serde::Serialize;
+
+use crate::{
+    config::ConfigState, http::HttpRoute, https::HttpsRoute, schema::route_checks, tls::TlsRoute,
+    x509, DbConn,
+};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+/// How often the background task looks at the config again while checks are disabled
+const DISABLED_POLL_INTERVAL: Duration = Duration::from_secs(60);
+/// How many targets are probed at the same time
+const CONCURRENT_CHECKS: usize = 8;
+
+#[derive(Serialize, Queryable, Insertable, Clone, Debug)]
+#[diesel(table_name = route_checks)]
+pub struct RouteCheck {
+    #[serde(skip)]
+    pub id: Option<i32>,
+    pub kind: String,
+    pub route_id: i32,
+    /// unix timestamp in seconds
+    pub checked_at: i64,
+    pub success: bool,
+    pub latency_ms: Option<i32>,
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Probe {
+    Http,
+    Tcp,
+}
+
+impl RouteCheck {
+    pub async fn for_kind(kind: &'static str, conn: &DbConn) -> QueryResult<HashMap<i32, Self>> {
+        let checks = conn
+            .run(move |c| {
+                route_checks::table
+                    .filter(route_checks::kind.eq(kind))
+                    .load::<RouteCheck>(c)
+            })
+            .await?;
+
+        Ok(checks
+            .into_iter()
+            .map(|check| (check.route_id, check))
+            .collect())
+    }
+
+    pub async fn delete(kind: &'static str, route_id: i32, conn: &DbConn) -> QueryResult<usize> {
+        conn.run(move |c| {
+            diesel::delete(route_checks::table)
+                .filter(route_checks::kind.eq(kind))
+                .filter(route_checks::route_id.eq(route_id))
+                .execute(c)
+        })
+        .await
+    }
+
+    async fn save(self, conn: &DbConn) -> QueryResult<usize> {
+        conn.run(move |c| {
+            diesel::insert_into(route_checks::table)
+                .values(&self)
+                .on_conflict((route_checks::kind, route_checks::route_id))
+                .do_update()
+                .set((
+                    route_checks::checked_at.eq(excluded(route_checks::checked_at)),
+                    route_checks::success.eq(excluded(route_checks::success)),
+                    route_checks::latency_ms.eq(excluded(route_checks::latency_ms)),
+                    route_checks::error.eq(excluded(route_checks::error)),
+                ))
+                .execute(c)
+        })
+        .await
+    }
+
+    /// Probes the target without storing the result
+    async fn probe(kind: &'static str, route_id: i32, probe: Probe, target: &str) -> Self {
+        let started = Instant::now();
+        let result = match probe {
+            Probe::Http => probe_http(target).await,
+            Probe::Tcp => probe_tcp(target).await,
+        };
+        let latency_ms = started.elapsed().as_millis().try_into().ok();
+
+        RouteCheck {
+            id: None,
+            kind: kind.into(),
+            route_id,
+            checked_at: x509::now(),
+            success: result.is_ok(),
+            latency_ms: result.is_ok().then_some(latency_ms).flatten(),
+            error: result.err(),
+        }
+    }
+
+    /// Probes the target and stores the result
+    pub async fn run(
+        kind: &'static str,
+        route_id: i32,
+        probe: Probe,
+        target: &str,
+        conn: &DbConn,
+    ) -> QueryResult<Self> {
+        let check = Self::probe(kind, route_id, probe, target).await;
+
+        check.clone().save(conn).await?;
+
+        Ok(check)
+    }
+}
+
+async fn probe_http(target: &str) -> Result<(), String> {
+    // backends behind a reverse proxy often use self-signed certificates or redirect,
+    // both still count as an answer
+    let client = reqwest::Client::builder()
+        .timeout(TIMEOUT)
+        .danger_accept_invalid_certs(true)
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let response = client.get(target).send().await.map_err(|e| e.to_string())?;
+
+    if response.status().is_server_error() {
+        Err(format!("HTTP {}", response.status()))
+    } else {
+        Ok(())
+    }
+}
+
+async fn probe_tcp(address: &str) -> Result<(), String> {
+    match timeout(TIMEOUT, TcpStream::connect(address)).await {
+        Ok(Ok(_)) => Ok(()),
+        Ok(Err(e)) => Err(e.to_string()),
+        Err(_) => Err("connection timed out".into()),
+    }
+}
+
+/// Checks the targets of all enabled routes, `CONCURRENT_CHECKS` at a time
+pub async fn check_all(conn: &DbConn) -> QueryResult<()> {
+    let mut targets = Vec::new();
+
+    for route in HttpRoute::all(conn).await? {
+        if let (true, Some(id)) = (route.enabled, route.id) {
+            targets.push(("http", id, Probe::Http, route.target));
+        }
+    }
+
+    for route in HttpsRoute::all(conn).await? {
+        if let (true, Some(id)) = (route.enabled, route.id) {
+            targets.push(("https", id, Probe::Http, route.target));
+        }
+    }
+
+    for route in TlsRoute::all(conn).await? {
+        if let (true, Some(id)) = (route.enabled, route.id) {
+            targets.push(("tls", id, Probe::Tcp, route.target_address()));
+        }
+    }
+
+    let probes: Vec<_> = targets
+        .into_iter()
+        .map(|(kind, id, probe, target)| async move {
+            RouteCheck::probe(kind, id, probe, &target).await
+        })
+        .collect();
+
+    let checks: Vec<RouteCheck> = stream::iter(probes)
+        .buffer_unordered(CONCURRENT_CHECKS)
+        .collect()
+        .await;
+
+    for check in checks {
+        check.save(conn).await?;
+    }
+
+    Ok(())
+}
+
+/// Starts the background task running the checks in the interval set in the config.
+/// Every pass takes a connection from the pool, so the task doesn't hold one while it sleeps.
+pub async fn start_background_checks(rocket: &Rocket<Orbit>) {
+    let pool = DbConn::pool(rocket).expect("database pool").clone();
+    let state = rocket.state::<ConfigState>().expect("config state").clone();
+
+    rocket::tokio::spawn(async move {
+        loop {
+            if state.config().check_interval != 0 {
+                match pool.get().await {
+                    Some(conn) => {
+                        if let Err(e) = check_all(&DbConn(conn)).await {
+                            error!("DB error running backend checks: {}", e);
+                        }
+                    }
+                    None => error!("no database connection for the backend checks"),
+                }
+            }
+
+            let interval = state.config().check_interval;
+            let sleep = if interval == 0 {
+                DISABLED_POLL_INTERVAL
+            } else {
+                Duration::from_secs(interval)
+            };
+            rocket::tokio::time::sleep(sleep).await;
+        }
+    });
+}
+
+fn flash_result(redirect: &str, result: QueryResult<RouteCheck>) -> Flash<Redirect> {
+    match result {
+        Ok(check) if check.success => Flash::success(
+            Redirect::to(redirect.to_string()),
+            format!(
+                "Target answered in {} ms",
+                check.latency_ms.unwrap_or_default()
+            ),
+        ),
+        Ok(check) => Flash::error(
+            Redirect::to(redirect.to_string()),
+            format!("Target not reachable: {}", check.error.unwrap_or_default()),
+        ),
+        Err(e) => Flash::error(Redirect::to(redirect.to_string()), e.to_string()),
+    }
+}
+
+#[post("/http/<id>/check")]
+pub async fn http(id: i32, conn: DbConn) -> Flash<Redirect> {
+    let result = match HttpRoute::get(id, &conn).await {
+        Ok(route) => RouteCheck::run("http", id, Probe::Http, &route.target, &conn).await,
+        Err(e) => Err(e),
+    };
+    flash_result("/http", result)
+}
+
+#[post("/https/<id>/check")]
+pub async fn https(id: i32, conn: DbConn) -> Flash<Redirect> {
+    let result = match HttpsRoute::get(id, &conn).await {
+        Ok(route) => RouteCheck::run("https", id, Probe::Http, &route.target, &conn).await,
+        Err(e) => Err(e),
+    };
+    flash_result("/https", result)
+}
+
+#[post("/tls/<id>/check")]
+pub async fn tls(id: i32, conn: DbConn) -> Flash<Redirect> {
+    let result = match TlsRoute::get(id, &conn).await {
+        Ok(route) => RouteCheck::run("tls", id, Probe::Tcp, &route.target_address(), &conn).await,
+        Err(e) => Err(e),
+    };
+    flash_result("/tls", result)
+}
+
+#[post("/check")]
+pub async fn all(conn: DbConn) -> Flash<Redirect> {
+    match check_all(&conn).await {
+        Ok(()) => Flash::success(Redirect::to("/"), "All targets checked"),
+        Err(e) => Flash::error(Redirect::to("/"), e.to_string()),
+    }
+}
@@ -1,4 +1,4 @@
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 
 use rocket::{
     form::Form,
@@ -10,8 +10,21 @@ use rocket_dyn_templates::Template;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+use crate::{
+    export_traefik_config,
+    output::{OutputFormat, OutputSplit},
+    traefik::{HttpCompress, HttpForwardAuth, HttpHeaders, HttpIpAllowList, HttpMiddleware},
+    DbConn,
+};
+
+const DEFAULT_COMPRESS: &str = "gui-default-compress";
+const DEFAULT_SECURITY_HEADERS: &str = "gui-default-security-headers";
+const DEFAULT_IP_ALLOW_LIST: &str = "gui-default-ip-allow-list";
+const FORWARD_AUTH: &str = "gui-forward-auth";
+
+#[derive(Clone)]
 pub struct ConfigState {
-    config: Mutex<Config>,
+    config: Arc<Mutex<Config>>,
 }
 
 #[derive(Debug, Error)]
@@ -38,13 +51,17 @@ impl ConfigState {
         let config: Config = serde_yaml::from_str(&serialized)?;
 
         Ok(Self {
-            config: Mutex::new(config),
+            config: Arc::new(Mutex::new(config)),
         })
     }
 
     pub fn save(&self, config: Config) -> Result<(), ConfigError> {
         let serialized = serde_yaml::to_string(&config)?;
+        self.write(config, &serialized)
+    }
 
+    /// Saves a config serialized by the caller, so serialization errors can be caught before other changes
+    pub fn write(&self, config: Config, serialized: &str) -> Result<(), ConfigError> {
         std::fs::write("./db/config.yaml", serialized).map_err(ConfigError::Save)?;
 
         let mut current = self.config.lock().unwrap();
@@ -59,14 +76,220 @@ impl ConfigState {
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, FromForm)]
+#[serde(default)]
 pub struct Config {
+    /// Certificate resolver of HTTPS routes using the default resolver
     pub acme_provider_name: String,
+    /// Additional certificate resolvers HTTPS routes can choose from
+    pub cert_resolvers: Vec<String>,
+    pub output_format: OutputFormat,
+    pub output_split: OutputSplit,
+    /// Bearer token required by the HTTP provider endpoint, empty to allow unauthenticated access
+    pub provider_token: String,
+    /// Redis URL to publish the config to, empty to disable
+    pub kv_url: String,
+    pub kv_root_key: String,
+    /// URL of the traefik API to show the live status of the routes, empty to disable
+    pub traefik_api_url: String,
+    /// Seconds between the background checks of all targets, 0 to disable
+    pub check_interval: u64,
+    /// Path of the acme.json of traefik to show the certificate inventory, empty to disable
+    pub acme_json_path: String,
+    /// Directory of the uploaded certificates inside the traefik container, empty to embed them in the config
+    pub cert_path: String,
+    pub proxy_protocol_mode: ProxyProtocolMode,
+    /// URL traefik reaches this GUI at, for the maintenance pages of routes
+    pub gui_url: String,
+    /// Apply the default middlewares to HTTP routes
+    pub default_middlewares_http: bool,
+    /// Apply the default middlewares to HTTPS routes
+    pub default_middlewares_https: bool,
+    pub default_compress: bool,
+    /// HSTS, frame and content type protection
+    pub default_security_headers: bool,
+    /// Comma separated IPs and CIDR ranges allowed to access the routes, empty to allow everyone
+    pub default_ip_allow_list: String,
+    /// Address of the forward auth server, e.g. Authelia or oauth2-proxy, empty to disable
+    pub forward_auth_address: String,
+    /// Trust the `X-Forwarded-*` headers of the requests
+    pub forward_auth_trust_forward_header: bool,
+    /// Comma separated headers of the auth response copied to the request, e.g. `Remote-User`
+    pub forward_auth_response_headers: String,
+}
+
+/// Where the PROXY protocol version of TLS routes is set in the traefik config
+#[derive(Serialize, Deserialize, FromFormField, Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ProxyProtocolMode {
+    /// `loadBalancer.proxyProtocol`, deprecated since traefik v3.2
+    #[default]
+    #[field(value = "load_balancer")]
+    LoadBalancer,
+    /// a TCP `serversTransport` per route, requires traefik v3.2
+    #[field(value = "servers_transport")]
+    ServersTransport,
+}
+
+impl Config {
+    /// All resolvers a route can select, starting with the default one
+    pub fn resolvers(&self) -> Vec<String> {
+        let mut resolvers = Vec::new();
+        for resolver in std::iter::once(&self.acme_provider_name).chain(&self.cert_resolvers) {
+            let resolver = resolver.trim();
+            if !resolver.is_empty() && !resolvers.iter().any(|r| r == resolver) {
+                resolvers.push(resolver.to_string());
+            }
+        }
+        resolvers
+    }
+
+    fn ip_allow_list(&self) -> Vec<String> {
+        self.default_ip_allow_list
+            .split(',')
+            .map(str::trim)
+            .filter(|range| !range.is_empty())
+            .map(String::from)
+            .collect()
+    }
+
+    fn forward_auth_response_headers(&self) -> Vec<String> {
+        self.forward_auth_response_headers
+            .split(',')
+            .map(str::trim)
+            .filter(|header| !header.is_empty())
+            .map(String::from)
+            .collect()
+    }
+
+    /// The middleware of the forward auth server, `None` without an address
+    pub fn forward_auth(&self) -> Option<(String, HttpMiddleware)> {
+        if self.forward_auth_address.is_empty() {
+            return None;
+        }
+
+        Some((
+            FORWARD_AUTH.to_string(),
+            HttpMiddleware {
+                forward_auth: Some(HttpForwardAuth {
+                    address: self.forward_auth_address.clone(),
+                    trust_forward_header: self.forward_auth_trust_forward_header,
+                    auth_response_headers: self.forward_auth_response_headers(),
+                }),
+                ..Default::default()
+            },
+        ))
+    }
+
+    /// Name of the forward auth middleware for the routers of protected routes.
+    /// Routes keep referencing it when the address is removed, so they fail instead of being exposed.
+    pub fn forward_auth_name(&self) -> String {
+        FORWARD_AUTH.to_string()
+    }
+
+    /// The middlewares applied to all routes, unless a route opts out
+    pub fn default_middlewares(&self) -> Vec<(String, HttpMiddleware)> {
+        let mut middlewares = Vec::new();
+
+        if self.default_compress {
+            middlewares.push((
+                DEFAULT_COMPRESS.to_string(),
+                HttpMiddleware {
+                    compress: Some(HttpCompress {}),
+                    ..Default::default()
+                },
+            ));
+        }
+
+        if self.default_security_headers {
+            middlewares.push((
+                DEFAULT_SECURITY_HEADERS.to_string(),
+                HttpMiddleware {
+                    headers: Some(HttpHeaders {
+                        frame_deny: true,
+                        content_type_nosniff: true,
+                        browser_xss_filter: true,
+                        referrer_policy: Some("strict-origin-when-cross-origin".into()),
+                        sts_seconds: Some(31536000),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                },
+            ));
+        }
+
+        let source_range = self.ip_allow_list();
+        if !source_range.is_empty() {
+            middlewares.push((
+                DEFAULT_IP_ALLOW_LIST.to_string(),
+                HttpMiddleware {
+                    ip_allow_list: Some(HttpIpAllowList { source_range }),
+                    ..Default::default()
+                },
+            ));
+        }
+
+        middlewares
+    }
+
+    /// Names of the default middlewares for the routers of HTTP or HTTPS routes
+    pub fn default_middleware_names(&self, https: bool) -> Vec<String> {
+        let enabled = if https {
+            self.default_middlewares_https
+        } else {
+            self.default_middlewares_http
+        };
+
+        if !enabled {
+            return Vec::new();
+        }
+
+        // the allow list comes first, so blocked requests aren't processed any further
+        let mut names = self
+            .default_middlewares()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>();
+        names.sort_by_key(|name| name != DEFAULT_IP_ALLOW_LIST);
+        names
+    }
+
+    pub fn cleanup(&mut self) {
+        let default = self.acme_provider_name.trim();
+        self.cert_resolvers = self
+            .resolvers()
+            .into_iter()
+            .filter(|resolver| resolver != default)
+            .collect();
+        self.default_ip_allow_list = self.ip_allow_list().join(", ");
+        self.forward_auth_address = self.forward_auth_address.trim().to_string();
+        self.forward_auth_response_headers = self.forward_auth_response_headers().join(", ");
+    }
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             acme_provider_name: "".into(),
+            cert_resolvers: Vec::new(),
+            output_format: OutputFormat::default(),
+            output_split: OutputSplit::default(),
+            provider_token: "".into(),
+            kv_url: "".into(),
+            kv_root_key: "traefik".into(),
+            traefik_api_url: "".into(),
+            check_interval: 0,
+            acme_json_path: "".into(),
+            cert_path: "/config/certs".into(),
+            proxy_protocol_mode: ProxyProtocolMode::default(),
+            gui_url: "http://gui:8000".into(),
+            default_middlewares_http: true,
+            default_middlewares_https: true,
+            default_compress: false,
+            default_security_headers: false,
+            default_ip_allow_list: "".into(),
+            forward_auth_address: "".into(),
+            forward_auth_trust_forward_header: false,
+            forward_auth_response_headers: "".into(),
         }
     }
 }
@@ -86,10 +309,16 @@ pub async fn index(state: &State<ConfigState>, flash: Option<FlashMessage<'_>>)
 }
 
 #[post("/config", data = "<config>")]
-pub async fn update(state: &State<ConfigState>, config: Form<Config>) -> Flash<Redirect> {
-    let config = config.into_inner();
+pub async fn update(
+    state: &State<ConfigState>,
+    config: Form<Config>,
+    conn: DbConn,
+) -> Flash<Redirect> {
+    let mut config = config.into_inner();
+    config.cleanup();
 
     state.save(config).unwrap();
 
+    export_traefik_config(&conn, &state.config()).await;
     Flash::success(Redirect::to("/config"), "Config updated")
 }
@@ -0,0 +1,368 @@
+//! Custom error pages, served by the GUI instead of the default pages of traefik.
+//!
+//! Every page becomes an `errors` middleware sending the matching responses to [`ERROR_SERVICE`].
+//! Global pages apply to all HTTP and HTTPS routes, other pages can be selected per route.
+
+use diesel::{Connection, ExpressionMethods, QueryDsl, QueryResult, RunQueryDsl};
+use rocket::{
+    form::Form,
+    fs::TempFile,
+    http::Status,
+    request::FlashMessage,
+    response::{content::RawHtml, Flash, Redirect},
+    tokio::io::AsyncReadExt,
+    State,
+};
+use rocket_dyn_templates::Template;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::{Config, ConfigState},
+    export_traefik_config,
+    schema::{error_pages, http_routes, https_routes},
+    traefik::{
+        HttpErrors, HttpLoadBalancer, HttpMiddleware, HttpServer, HttpService, TraefikConfig,
+    },
+    DbConn,
+};
+
+/// Service of the GUI answering the error pages
+pub const ERROR_SERVICE: &str = "gui-errors";
+
+/// Replaced with the status code in the HTML of a page
+const STATUS_PLACEHOLDER: &str = "{status}";
+
+#[derive(Serialize, Deserialize, Queryable, Insertable, AsChangeset, FromForm, Clone, Debug)]
+#[serde(crate = "rocket::serde")]
+#[diesel(table_name = error_pages)]
+pub struct ErrorPage {
+    pub id: Option<i32>,
+    pub name: String,
+    /// Comma separated status codes and ranges, e.g. `404, 500-599`
+    pub status: String,
+    pub html: String,
+    /// Used by all routes, not only the ones selecting it
+    pub global: bool,
+}
+
+impl ErrorPage {
+    pub async fn all(conn: &DbConn) -> QueryResult<Vec<ErrorPage>> {
+        conn.run(|c| error_pages::table.load::<ErrorPage>(c)).await
+    }
+
+    pub async fn get(id: i32, conn: &DbConn) -> QueryResult<ErrorPage> {
+        conn.run(move |c| error_pages::table.filter(error_pages::id.eq(id)).first(c))
+            .await
+    }
+
+    pub async fn names(conn: &DbConn) -> QueryResult<Vec<String>> {
+        conn.run(|c| {
+            error_pages::table
+                .select(error_pages::name)
+                .order(error_pages::name)
+                .load::<String>(c)
+        })
+        .await
+    }
+
+    pub async fn insert(mut page: ErrorPage, conn: &DbConn) -> QueryResult<usize> {
+        page.cleanup();
+        conn.run(move |c| {
+            diesel::insert_into(error_pages::table)
+                .values(&page)
+                .execute(c)
+        })
+        .await
+    }
+
+    /// Updates the page and the routes using it, in case it was renamed
+    pub async fn update(id: i32, mut page: ErrorPage, conn: &DbConn) -> QueryResult<usize> {
+        page.cleanup();
+        conn.run(move |c| {
+            c.transaction(|c| {
+                let name = error_pages::table
+                    .filter(error_pages::id.eq(id))
+                    .select(error_pages::name)
+                    .first::<String>(c)?;
+
+                diesel::update(http_routes::table)
+                    .filter(http_routes::error_page.eq(&name))
+                    .set(http_routes::error_page.eq(&page.name))
+                    .execute(c)?;
+                diesel::update(https_routes::table)
+                    .filter(https_routes::error_page.eq(&name))
+                    .set(https_routes::error_page.eq(&page.name))
+                    .execute(c)?;
+
+                diesel::update(error_pages::table)
+                    .filter(error_pages::id.eq(id))
+                    .set(&page)
+                    .execute(c)
+            })
+        })
+        .await
+    }
+
+    /// Deletes the page, routes using it fall back to the global pages
+    pub async fn delete(id: i32, conn: &DbConn) -> QueryResult<usize> {
+        conn.run(move |c| {
+            c.transaction(|c| {
+                let name = error_pages::table
+                    .filter(error_pages::id.eq(id))
+                    .select(error_pages::name)
+                    .first::<String>(c)?;
+
+                diesel::update(http_routes::table)
+                    .filter(http_routes::error_page.eq(&name))
+                    .set(http_routes::error_page.eq(None::<String>))
+                    .execute(c)?;
+                diesel::update(https_routes::table)
+                    .filter(https_routes::error_page.eq(&name))
+                    .set(https_routes::error_page.eq(None::<String>))
+                    .execute(c)?;
+
+                diesel::delete(error_pages::table)
+                    .filter(error_pages::id.eq(id))
+                    .execute(c)
+            })
+        })
+        .await
+    }
+
+    pub fn cleanup(&mut self) {
+        self.name = self.name.trim().to_string();
+        self.status = self
+            .status
+            .split(',')
+            .map(str::trim)
+            .filter(|status| !status.is_empty())
+            .collect::<Vec<_>>()
+            .join(", ");
+    }
+
+    /// Checks the status codes, e.g. `404` or `500-599`
+    pub fn validate(&self) -> Result<(), String> {
+        let parse = |code: &str| {
+            code.trim()
+                .parse::<u16>()
+                .ok()
+                .filter(|code| (100..=599).contains(code))
+                .ok_or_else(|| format!("invalid status code {}", code.trim()))
+        };
+
+        if self.status.is_empty() {
+            return Err("an error page needs at least one status code".into());
+        }
+
+        for status in self.status.split(',') {
+            match status.split_once('-') {
+                Some((from, to)) => {
+                    if parse(from)? > parse(to)? {
+                        return Err(format!("invalid status range {}", status.trim()));
+                    }
+                }
+                None => {
+                    parse(status)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn middleware_name(&self) -> String {
+        format!("gui-errors-{}-{}", self.id.unwrap_or_default(), self.name)
+    }
+
+    /// Adds the middlewares of all pages and the service serving them
+    pub async fn generate_traefik_config(
+        traefik_config: &mut TraefikConfig,
+        conn: &DbConn,
+        config: &Config,
+    ) {
+        let pages = ErrorPage::all(conn).await.unwrap();
+
+        if pages.is_empty() || config.gui_url.trim().is_empty() {
+            return;
+        }
+
+        traefik_config.http.services.insert(
+            ERROR_SERVICE.into(),
+            HttpService {
+                load_balancer: Some(HttpLoadBalancer {
+                    servers: vec![HttpServer {
+                        url: config.gui_url.trim_end_matches('/').to_string(),
+                    }],
+                    health_check: None,
+                }),
+                ..Default::default()
+            },
+        );
+
+        for page in pages {
+            traefik_config.http.middlewares.insert(
+                page.middleware_name(),
+                HttpMiddleware {
+                    errors: Some(HttpErrors {
+                        status: page.status.split(',').map(|s| s.trim().into()).collect(),
+                        service: ERROR_SERVICE.into(),
+                        query: format!("/errors/{}/{{status}}", page.id.unwrap_or_default()),
+                    }),
+                    ..Default::default()
+                },
+            );
+        }
+    }
+}
+
+/// Middlewares of the global pages and the page selected by a route
+pub fn route_middlewares(
+    pages: &[ErrorPage],
+    selected: Option<&str>,
+    config: &Config,
+) -> Vec<String> {
+    if config.gui_url.trim().is_empty() {
+        return Vec::new();
+    }
+
+    pages
+        .iter()
+        .filter(|page| page.global || Some(page.name.as_str()) == selected)
+        .map(ErrorPage::middleware_name)
+        .collect()
+}
+
+#[derive(Serialize)]
+struct ErrorPages {
+    flash: Option<(String, String)>,
+    pages: Vec<ErrorPage>,
+    edit: Option<i32>,
+    placeholder: &'static str,
+}
+
+impl ErrorPages {
+    pub async fn raw(conn: &DbConn, flash: Option<(String, String)>, edit: Option<i32>) -> Self {
+        let (pages, flash) = match ErrorPage::all(conn).await {
+            Ok(pages) => (pages, flash),
+            Err(e) => {
+                error!("DB error loading error pages: {}", e);
+                (Vec::new(), Some(("error".into(), e.to_string())))
+            }
+        };
+
+        Self {
+            flash,
+            pages,
+            edit,
+            placeholder: STATUS_PLACEHOLDER,
+        }
+    }
+}
+
+#[derive(FromForm)]
+pub struct ErrorPageForm<'r> {
+    name: String,
+    status: String,
+    global: bool,
+    page: TempFile<'r>,
+}
+
+async fn read_html(file: &TempFile<'_>) -> std::io::Result<String> {
+    let mut html = String::new();
+    file.open().await?.read_to_string(&mut html).await?;
+    Ok(html)
+}
+
+#[get("/error_pages?<edit>")]
+pub async fn index(edit: Option<i32>, flash: Option<FlashMessage<'_>>, conn: DbConn) -> Template {
+    let flash = flash.map(FlashMessage::into_inner);
+    Template::render("error_pages", ErrorPages::raw(&conn, flash, edit).await)
+}
+
+#[post("/error_pages", data = "<page_form>")]
+pub async fn create(
+    page_form: Form<ErrorPageForm<'_>>,
+    conn: DbConn,
+    config: &State<ConfigState>,
+) -> Flash<Redirect> {
+    let html = match read_html(&page_form.page).await {
+        Ok(html) => html,
+        Err(e) => return Flash::error(Redirect::to("/error_pages"), e.to_string()),
+    };
+
+    let mut page = ErrorPage {
+        id: None,
+        name: page_form.name.clone(),
+        status: page_form.status.clone(),
+        html,
+        global: page_form.global,
+    };
+    page.cleanup();
+
+    if let Err(e) = page.validate() {
+        return Flash::error(Redirect::to("/error_pages"), e);
+    }
+
+    if let Err(e) = ErrorPage::insert(page, &conn).await {
+        Flash::error(Redirect::to("/error_pages"), e.to_string())
+    } else {
+        export_traefik_config(&conn, &config.config()).await;
+        Flash::success(Redirect::to("/error_pages"), "Error page uploaded")
+    }
+}
+
+#[post("/error_pages/<id>", data = "<page_form>")]
+pub async fn update(
+    id: i32,
+    page_form: Form<ErrorPage>,
+    conn: DbConn,
+    config: &State<ConfigState>,
+) -> Flash<Redirect> {
+    let mut page = page_form.into_inner();
+    page.cleanup();
+
+    if let Err(e) = page.validate() {
+        return Flash::error(Redirect::to(format!("/error_pages?edit={}", id)), e);
+    }
+
+    if let Err(e) = ErrorPage::update(id, page, &conn).await {
+        Flash::error(Redirect::to("/error_pages"), e.to_string())
+    } else {
+        export_traefik_config(&conn, &config.config()).await;
+        Flash::success(Redirect::to("/error_pages"), "Error page updated")
+    }
+}
+
+#[post("/error_pages/<id>/delete", data = "<confirm>")]
+pub async fn delete(
+    id: i32,
+    confirm: Form<bool>,
+    conn: DbConn,
+    config: &State<ConfigState>,
+) -> Flash<Redirect> {
+    if confirm.into_inner() {
+        if let Err(e) = ErrorPage::delete(id, &conn).await {
+            Flash::error(Redirect::to("/error_pages"), e.to_string())
+        } else {
+            export_traefik_config(&conn, &config.config()).await;
+            Flash::success(Redirect::to("/error_pages"), "Error page deleted")
+        }
+    } else {
+        Flash::error(Redirect::to("/error_pages"), "Delete cancelled")
+    }
+}
+
+/// The page traefik requests for an error response, answered with the original status code
+#[get("/errors/<id>/<status>")]
+pub async fn page(id: i32, status: u16, conn: DbConn) -> Option<(Status, RawHtml<String>)> {
+    let page = ErrorPage::get(id, &conn).await.ok()?;
+    let status = Status::from_code(status).unwrap_or(Status::InternalServerError);
+
+    Some((
+        status,
+        RawHtml(
+            page.html
+                .replace(STATUS_PLACEHOLDER, &status.code.to_string()),
+        ),
+    ))
+}
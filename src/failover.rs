@@ -0,0 +1,94 @@
+//! Failover of HTTP and HTTPS routes to a standby target, e.g. a second VM or a static status page.
+//!
+//! Traefik checks the health of the route target and sends the requests to the fallback
+//! while the check fails. Without a fallback the health check only takes the target out of rotation.
+
+use crate::traefik::{
+    HttpFailover, HttpHealthCheck, HttpLoadBalancer, HttpServer, HttpService, TraefikConfig,
+};
+
+/// The failover settings of a route
+pub struct Failover<'a> {
+    /// Target used while the health check fails
+    pub fallback_target: Option<&'a str>,
+    /// Path of the route target answering with `2xx` or `3xx` while it is healthy
+    pub health_check_path: Option<&'a str>,
+    /// Seconds between the health checks, traefik checks every 30 seconds if not set
+    pub health_check_interval: Option<i32>,
+}
+
+impl Failover<'_> {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.fallback_target.is_some() && self.health_check_path.is_none() {
+            return Err("a fallback target needs a health check path".into());
+        }
+
+        if self
+            .health_check_path
+            .is_some_and(|path| !path.starts_with('/'))
+        {
+            return Err("the health check path has to start with /".into());
+        }
+
+        if self
+            .health_check_interval
+            .is_some_and(|interval| interval < 1)
+        {
+            return Err("the health check interval has to be at least 1".into());
+        }
+
+        Ok(())
+    }
+
+    /// The health check of the load balancer of the route target
+    pub fn health_check(&self) -> Option<HttpHealthCheck> {
+        self.health_check_path.map(|path| HttpHealthCheck {
+            path: path.to_string(),
+            interval: self
+                .health_check_interval
+                .map(|seconds| format!("{}s", seconds)),
+        })
+    }
+
+    /// Adds the fallback service and the failover service in front of `service`.
+    /// Returns the service for the router, `service` itself without a fallback.
+    pub fn add_to_config(
+        &self,
+        traefik_config: &mut TraefikConfig,
+        router_name: &str,
+        service: &str,
+    ) -> String {
+        let Some(fallback_target) = self.fallback_target else {
+            return service.to_string();
+        };
+
+        let name = format!("{}-failover", router_name);
+        let fallback = format!("{}-fallback", router_name);
+
+        traefik_config.http.services.insert(
+            fallback.clone(),
+            HttpService {
+                load_balancer: Some(HttpLoadBalancer {
+                    servers: vec![HttpServer {
+                        url: fallback_target.to_string(),
+                    }],
+                    health_check: None,
+                }),
+                ..Default::default()
+            },
+        );
+
+        traefik_config.http.services.insert(
+            name.clone(),
+            HttpService {
+                failover: Some(HttpFailover {
+                    service: service.to_string(),
+                    fallback,
+                }),
+                ..Default::default()
+            },
+        );
+
+        name
+    }
+}
@@ -0,0 +1,421 @@
+//! Named security header profiles, e.g. HSTS, a content security policy or CORS allow-lists.
+//!
+//! Every profile becomes a `headers` middleware, HTTPS routes can select one.
+
+use std::collections::BTreeMap;
+
+use diesel::{Connection, ExpressionMethods, QueryDsl, QueryResult, RunQueryDsl};
+use rocket::{
+    form::Form,
+    request::FlashMessage,
+    response::{Flash, Redirect},
+    State,
+};
+use rocket_dyn_templates::Template;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    config::ConfigState,
+    export_traefik_config,
+    schema::{header_profiles, https_routes},
+    traefik::{HttpHeaders, HttpMiddleware},
+    DbConn,
+};
+
+pub const PRESETS: [&str; 3] = ["strict", "basic", "cors"];
+
+#[derive(Debug, Error)]
+pub enum HeaderProfileError {
+    #[error("the header profile is used by {0}, select another profile for these routes first")]
+    InUse(String),
+    #[error(transparent)]
+    Db(#[from] diesel::result::Error),
+}
+
+#[derive(Serialize, Deserialize, Queryable, Insertable, AsChangeset, FromForm, Clone, Debug)]
+#[serde(crate = "rocket::serde")]
+#[diesel(table_name = header_profiles, treat_none_as_null = true)]
+pub struct HeaderProfile {
+    pub id: Option<i32>,
+    pub name: String,
+    /// `max-age` of the HSTS header, `None` doesn't send it
+    pub sts_seconds: Option<i32>,
+    pub sts_include_subdomains: bool,
+    pub sts_preload: bool,
+    pub frame_deny: bool,
+    pub content_type_nosniff: bool,
+    pub browser_xss_filter: bool,
+    pub referrer_policy: Option<String>,
+    pub content_security_policy: Option<String>,
+    /// One `Name: value` per line, an empty value removes the header
+    pub custom_request_headers: Option<String>,
+    /// One `Name: value` per line, an empty value removes the header
+    pub custom_response_headers: Option<String>,
+    /// Comma separated origins allowed to make cross-origin requests
+    pub cors_allow_origins: Option<String>,
+    /// Comma separated methods allowed for cross-origin requests
+    pub cors_allow_methods: Option<String>,
+    /// Comma separated headers allowed for cross-origin requests
+    pub cors_allow_headers: Option<String>,
+    pub cors_allow_credentials: bool,
+}
+
+fn split_list(list: &Option<String>) -> Vec<String> {
+    list.iter()
+        .flat_map(|list| list.split(','))
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Parses one `Name: value` header per line
+fn parse_headers(headers: &Option<String>) -> Result<BTreeMap<String, String>, String> {
+    headers
+        .iter()
+        .flat_map(|headers| headers.lines())
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| match line.split_once(':') {
+            Some((name, value)) if !name.trim().is_empty() => {
+                Ok((name.trim().to_string(), value.trim().to_string()))
+            }
+            _ => Err(format!("invalid header {}, expected Name: value", line)),
+        })
+        .collect()
+}
+
+impl HeaderProfile {
+    /// A new profile with the settings of a preset, see [`PRESETS`]
+    pub fn preset(name: String, preset: &str) -> Option<Self> {
+        let profile = Self {
+            id: None,
+            name,
+            sts_seconds: None,
+            sts_include_subdomains: false,
+            sts_preload: false,
+            frame_deny: false,
+            content_type_nosniff: true,
+            browser_xss_filter: false,
+            referrer_policy: None,
+            content_security_policy: None,
+            custom_request_headers: None,
+            custom_response_headers: None,
+            cors_allow_origins: None,
+            cors_allow_methods: None,
+            cors_allow_headers: None,
+            cors_allow_credentials: false,
+        };
+
+        match preset {
+            // what security audits and the HSTS preload list expect
+            "strict" => Some(Self {
+                sts_seconds: Some(63072000),
+                sts_include_subdomains: true,
+                sts_preload: true,
+                frame_deny: true,
+                browser_xss_filter: true,
+                referrer_policy: Some("no-referrer".into()),
+                content_security_policy: Some(
+                    "default-src 'self'; frame-ancestors 'none'; base-uri 'self'; form-action 'self'"
+                        .into(),
+                ),
+                custom_response_headers: Some("Server:\nX-Powered-By:".into()),
+                ..profile
+            }),
+            "basic" => Some(Self {
+                sts_seconds: Some(31536000),
+                frame_deny: true,
+                referrer_policy: Some("strict-origin-when-cross-origin".into()),
+                ..profile
+            }),
+            // for APIs called from other origins, the origins have to be filled in
+            "cors" => Some(Self {
+                cors_allow_methods: Some("GET, POST, PUT, PATCH, DELETE, OPTIONS".into()),
+                cors_allow_headers: Some("Authorization, Content-Type".into()),
+                ..profile
+            }),
+            _ => None,
+        }
+    }
+
+    pub async fn all(conn: &DbConn) -> QueryResult<Vec<HeaderProfile>> {
+        conn.run(|c| header_profiles::table.load::<HeaderProfile>(c))
+            .await
+    }
+
+    pub async fn names(conn: &DbConn) -> QueryResult<Vec<String>> {
+        conn.run(|c| {
+            header_profiles::table
+                .select(header_profiles::name)
+                .order(header_profiles::name)
+                .load::<String>(c)
+        })
+        .await
+    }
+
+    pub async fn insert(mut profile: HeaderProfile, conn: &DbConn) -> QueryResult<usize> {
+        profile.cleanup();
+        conn.run(move |c| {
+            diesel::insert_into(header_profiles::table)
+                .values(&profile)
+                .execute(c)
+        })
+        .await
+    }
+
+    /// Updates the profile and the routes using it, in case it was renamed
+    pub async fn update(id: i32, mut profile: HeaderProfile, conn: &DbConn) -> QueryResult<usize> {
+        profile.cleanup();
+        conn.run(move |c| {
+            c.transaction(|c| {
+                let name = header_profiles::table
+                    .filter(header_profiles::id.eq(id))
+                    .select(header_profiles::name)
+                    .first::<String>(c)?;
+
+                diesel::update(https_routes::table)
+                    .filter(https_routes::header_profile.eq(&name))
+                    .set(https_routes::header_profile.eq(&profile.name))
+                    .execute(c)?;
+
+                diesel::update(header_profiles::table)
+                    .filter(header_profiles::id.eq(id))
+                    .set(&profile)
+                    .execute(c)
+            })
+        })
+        .await
+    }
+
+    /// Deletes the profile. Profiles used by routes can't be deleted,
+    /// the routes would silently lose e.g. HSTS or the content security policy.
+    pub async fn delete(id: i32, conn: &DbConn) -> Result<usize, HeaderProfileError> {
+        conn.run(move |c| {
+            c.transaction(|c| {
+                let name = header_profiles::table
+                    .filter(header_profiles::id.eq(id))
+                    .select(header_profiles::name)
+                    .first::<String>(c)?;
+
+                let routes = https_routes::table
+                    .filter(https_routes::header_profile.eq(&name))
+                    .select(https_routes::name)
+                    .load::<String>(c)?;
+                if !routes.is_empty() {
+                    return Err(HeaderProfileError::InUse(
+                        routes
+                            .iter()
+                            .map(|route| format!("HTTPS route {}", route))
+                            .collect::<Vec<_>>()
+                            .join(", "),
+                    ));
+                }
+
+                Ok(diesel::delete(header_profiles::table)
+                    .filter(header_profiles::id.eq(id))
+                    .execute(c)?)
+            })
+        })
+        .await
+    }
+
+    pub fn cleanup(&mut self) {
+        self.name = self.name.trim().to_string();
+        for field in [
+            &mut self.referrer_policy,
+            &mut self.content_security_policy,
+            &mut self.custom_request_headers,
+            &mut self.custom_response_headers,
+            &mut self.cors_allow_origins,
+            &mut self.cors_allow_methods,
+            &mut self.cors_allow_headers,
+        ] {
+            if field.as_ref().is_some_and(|value| value.trim().is_empty()) {
+                *field = None;
+            }
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        // the name is part of the middleware name, `@` would refer to another provider
+        if self.name.is_empty()
+            || self
+                .name
+                .chars()
+                .any(|c| c == '@' || c == '/' || c.is_whitespace())
+        {
+            return Err("the name can't be empty or contain @, / or spaces".into());
+        }
+
+        parse_headers(&self.custom_request_headers)?;
+        parse_headers(&self.custom_response_headers)?;
+
+        if self.sts_seconds.is_some_and(|seconds| seconds < 0) {
+            return Err("the HSTS max-age can't be negative".into());
+        }
+
+        let origins = split_list(&self.cors_allow_origins);
+        if self.cors_allow_credentials && origins.iter().any(|origin| origin == "*") {
+            return Err("browsers reject credentials for the wildcard origin".into());
+        }
+
+        Ok(())
+    }
+
+    pub fn middleware_name(&self) -> String {
+        format!("gui-headers-{}-{}", self.id.unwrap_or_default(), self.name)
+    }
+
+    fn middleware(&self) -> HttpMiddleware {
+        let access_control_allow_origin_list = split_list(&self.cors_allow_origins);
+
+        HttpMiddleware {
+            headers: Some(HttpHeaders {
+                frame_deny: self.frame_deny,
+                content_type_nosniff: self.content_type_nosniff,
+                browser_xss_filter: self.browser_xss_filter,
+                referrer_policy: self.referrer_policy.clone(),
+                sts_seconds: self.sts_seconds.map(|seconds| seconds as u64),
+                sts_include_subdomains: self.sts_include_subdomains,
+                sts_preload: self.sts_preload,
+                content_security_policy: self.content_security_policy.clone(),
+                // validated when saving
+                custom_request_headers: parse_headers(&self.custom_request_headers)
+                    .unwrap_or_default(),
+                custom_response_headers: parse_headers(&self.custom_response_headers)
+                    .unwrap_or_default(),
+                // the allowed origin depends on the request, so caches have to vary by it
+                add_vary_header: !access_control_allow_origin_list.is_empty(),
+                access_control_allow_origin_list,
+                access_control_allow_methods: split_list(&self.cors_allow_methods),
+                access_control_allow_headers: split_list(&self.cors_allow_headers),
+                access_control_allow_credentials: self.cors_allow_credentials,
+            }),
+            ..Default::default()
+        }
+    }
+
+    pub async fn generate_traefik_config(conn: &DbConn) -> BTreeMap<String, HttpMiddleware> {
+        let profiles = HeaderProfile::all(conn).await.unwrap();
+
+        profiles
+            .iter()
+            .map(|profile| (profile.middleware_name(), profile.middleware()))
+            .collect()
+    }
+}
+
+/// Middleware of the profile selected by a route
+pub fn route_middleware(profiles: &[HeaderProfile], selected: Option<&str>) -> Option<String> {
+    profiles
+        .iter()
+        .find(|profile| Some(profile.name.as_str()) == selected)
+        .map(HeaderProfile::middleware_name)
+}
+
+#[derive(Serialize)]
+struct HeaderProfiles {
+    flash: Option<(String, String)>,
+    profiles: Vec<HeaderProfile>,
+    edit: Option<i32>,
+    presets: [&'static str; 3],
+}
+
+impl HeaderProfiles {
+    pub async fn raw(conn: &DbConn, flash: Option<(String, String)>, edit: Option<i32>) -> Self {
+        let (profiles, flash) = match HeaderProfile::all(conn).await {
+            Ok(profiles) => (profiles, flash),
+            Err(e) => {
+                error!("DB error loading header profiles: {}", e);
+                (Vec::new(), Some(("error".into(), e.to_string())))
+            }
+        };
+
+        Self {
+            flash,
+            profiles,
+            edit,
+            presets: PRESETS,
+        }
+    }
+}
+
+#[derive(FromForm)]
+pub struct PresetForm {
+    name: String,
+    preset: String,
+}
+
+#[get("/headers?<edit>")]
+pub async fn index(edit: Option<i32>, flash: Option<FlashMessage<'_>>, conn: DbConn) -> Template {
+    let flash = flash.map(FlashMessage::into_inner);
+    Template::render("headers", HeaderProfiles::raw(&conn, flash, edit).await)
+}
+
+#[post("/headers", data = "<preset_form>")]
+pub async fn create(
+    preset_form: Form<PresetForm>,
+    conn: DbConn,
+    config: &State<ConfigState>,
+) -> Flash<Redirect> {
+    let preset_form = preset_form.into_inner();
+
+    let Some(mut profile) = HeaderProfile::preset(preset_form.name, &preset_form.preset) else {
+        return Flash::error(Redirect::to("/headers"), "Unknown preset");
+    };
+    profile.cleanup();
+
+    if let Err(e) = profile.validate() {
+        return Flash::error(Redirect::to("/headers"), e);
+    }
+
+    if let Err(e) = HeaderProfile::insert(profile, &conn).await {
+        Flash::error(Redirect::to("/headers"), e.to_string())
+    } else {
+        export_traefik_config(&conn, &config.config()).await;
+        Flash::success(Redirect::to("/headers"), "Header profile created")
+    }
+}
+
+#[post("/headers/<id>", data = "<profile_form>")]
+pub async fn update(
+    id: i32,
+    profile_form: Form<HeaderProfile>,
+    conn: DbConn,
+    config: &State<ConfigState>,
+) -> Flash<Redirect> {
+    let mut profile = profile_form.into_inner();
+    profile.cleanup();
+
+    if let Err(e) = profile.validate() {
+        return Flash::error(Redirect::to(format!("/headers?edit={}", id)), e);
+    }
+
+    if let Err(e) = HeaderProfile::update(id, profile, &conn).await {
+        Flash::error(Redirect::to("/headers"), e.to_string())
+    } else {
+        export_traefik_config(&conn, &config.config()).await;
+        Flash::success(Redirect::to("/headers"), "Header profile updated")
+    }
+}
+
+#[post("/headers/<id>/delete", data = "<confirm>")]
+pub async fn delete(
+    id: i32,
+    confirm: Form<bool>,
+    conn: DbConn,
+    config: &State<ConfigState>,
+) -> Flash<Redirect> {
+    if confirm.into_inner() {
+        if let Err(e) = HeaderProfile::delete(id, &conn).await {
+            Flash::error(Redirect::to("/headers"), e.to_string())
+        } else {
+            export_traefik_config(&conn, &config.config()).await;
+            Flash::success(Redirect::to("/headers"), "Header profile deleted")
+        }
+    } else {
+        Flash::error(Redirect::to("/headers"), "Delete cancelled")
+    }
+}
@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 use diesel::{ExpressionMethods, QueryDsl, QueryResult, RunQueryDsl};
 use itertools::Itertools;
 use rocket::{
@@ -7,22 +9,29 @@ use rocket::{
     State,
 };
 use rocket_dyn_templates::Template;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    config::ConfigState,
+    auth::{self, AuthRealm},
+    check::RouteCheck,
+    config::{Config, ConfigState},
+    error_pages::{self, ErrorPage},
     export_traefik_config,
+    failover::Failover,
     https::HttpsRoute,
+    limits::{self, RouteLimits},
+    maintenance,
+    rewrite::PathRewrite,
     schema::http_routes::{self, dsl},
-    traefik::{HttpConfig, HttpLoadBalancer, HttpRouter, HttpServer, HttpService},
+    status::{self, RouteView},
+    traefik::{HttpLoadBalancer, HttpRouter, HttpServer, HttpService, TraefikConfig},
     DbConn,
 };
 
-#[derive(Serialize, Queryable, Insertable, AsChangeset, FromForm, Clone, Debug)]
+#[derive(Serialize, Deserialize, Queryable, Insertable, AsChangeset, FromForm, Clone, Debug)]
 #[serde(crate = "rocket::serde")]
-#[diesel(table_name = http_routes)]
+#[diesel(table_name = http_routes, treat_none_as_null = true)]
 pub struct HttpRoute {
-    #[serde(skip_deserializing)]
     pub id: Option<i32>,
     pub enabled: bool,
     pub name: String,
@@ -31,6 +40,60 @@ pub struct HttpRoute {
     pub host_regex: bool,
     pub host: String,
     pub prefix: Option<String>,
+    /// Serve the maintenance page of the GUI instead of the target
+    #[serde(default)]
+    pub maintenance: bool,
+    /// Custom HTML of the maintenance page, `None` uses the built-in page
+    #[serde(default)]
+    pub maintenance_html: Option<String>,
+    /// Name of an error page used in addition to the global ones
+    #[serde(default)]
+    pub error_page: Option<String>,
+    /// Don't apply the default middlewares from the config
+    #[serde(default)]
+    pub skip_default_middlewares: bool,
+    /// Remove the path prefix before forwarding to the target
+    #[serde(default)]
+    pub strip_prefix: bool,
+    /// Prefix added to the path before forwarding to the target
+    #[serde(default)]
+    pub add_prefix: Option<String>,
+    /// Regex matching the path, replaced with `path_replacement`
+    #[serde(default)]
+    pub path_regex: Option<String>,
+    #[serde(default)]
+    pub path_replacement: Option<String>,
+    /// Name of the realm whose users can access the route
+    #[serde(default)]
+    pub auth_realm: Option<String>,
+    /// Check the requests with the forward auth server from the config
+    #[serde(default)]
+    pub forward_auth: bool,
+    /// Requests per period on average, `None` disables the rate limit
+    #[serde(default)]
+    pub rate_limit_average: Option<i32>,
+    #[serde(default)]
+    pub rate_limit_burst: Option<i32>,
+    /// Seconds, see [`crate::limits::RouteLimits::rate_limit_period`]
+    #[serde(default)]
+    pub rate_limit_period: Option<i32>,
+    /// What the limits group the requests by, see [`crate::limits::SOURCES`]
+    #[serde(default)]
+    pub limit_source: Option<String>,
+    #[serde(default)]
+    pub limit_header: Option<String>,
+    /// Requests processed at the same time, `None` disables the limit
+    #[serde(default)]
+    pub in_flight_requests: Option<i32>,
+    /// Target used while the health check of the route target fails
+    #[serde(default)]
+    pub fallback_target: Option<String>,
+    /// Path checked on the route target, `None` disables the health check
+    #[serde(default)]
+    pub health_check_path: Option<String>,
+    /// Seconds between the health checks
+    #[serde(default)]
+    pub health_check_interval: Option<i32>,
 }
 
 impl HttpRoute {
@@ -70,6 +133,7 @@ impl HttpRoute {
     }
 
     pub async fn delete(id: i32, conn: &DbConn) -> QueryResult<usize> {
+        RouteCheck::delete("http", id, conn).await?;
         conn.run(move |c| {
             diesel::delete(http_routes::table)
                 .filter(http_routes::id.eq(id))
@@ -88,23 +152,84 @@ impl HttpRoute {
         .await
     }
 
+    pub async fn set_maintenance(id: i32, maintenance: bool, conn: &DbConn) -> QueryResult<usize> {
+        conn.run(move |c| {
+            diesel::update(http_routes::table)
+                .filter(http_routes::id.eq(id))
+                .set(http_routes::maintenance.eq(maintenance))
+                .execute(c)
+        })
+        .await
+    }
+
+    pub fn router_name(&self) -> String {
+        format!("gui-http-{}-{}", self.id.unwrap_or_default(), self.name)
+    }
+
+    pub fn path_rewrite(&self) -> PathRewrite<'_> {
+        PathRewrite {
+            prefix: self.prefix.as_deref(),
+            strip_prefix: self.strip_prefix,
+            add_prefix: self.add_prefix.as_deref(),
+            path_regex: self.path_regex.as_deref(),
+            path_replacement: self.path_replacement.as_deref(),
+        }
+    }
+
+    pub fn limits(&self) -> RouteLimits<'_> {
+        RouteLimits {
+            rate_limit_average: self.rate_limit_average,
+            rate_limit_burst: self.rate_limit_burst,
+            rate_limit_period: self.rate_limit_period,
+            limit_source: self.limit_source.as_deref(),
+            limit_header: self.limit_header.as_deref(),
+            in_flight_requests: self.in_flight_requests,
+        }
+    }
+
+    pub fn failover(&self) -> Failover<'_> {
+        Failover {
+            fallback_target: self.fallback_target.as_deref(),
+            health_check_path: self.health_check_path.as_deref(),
+            health_check_interval: self.health_check_interval,
+        }
+    }
+
     pub fn cleanup(&mut self) {
-        if let Some(prefix) = &self.prefix {
-            if prefix.trim().is_empty() {
-                self.prefix = None;
+        for field in [
+            &mut self.prefix,
+            &mut self.maintenance_html,
+            &mut self.error_page,
+            &mut self.add_prefix,
+            &mut self.path_regex,
+            &mut self.path_replacement,
+            &mut self.auth_realm,
+            &mut self.limit_source,
+            &mut self.limit_header,
+            &mut self.fallback_target,
+            &mut self.health_check_path,
+        ] {
+            if field.as_ref().is_some_and(|value| value.trim().is_empty()) {
+                *field = None;
             }
         }
     }
 
-    pub async fn generate_traefik_config(conn: &DbConn) -> HttpConfig {
-        let mut config = HttpConfig::new();
+    pub async fn generate_traefik_config(
+        conn: &DbConn,
+        app_config: &Config,
+    ) -> BTreeMap<String, TraefikConfig> {
+        let mut configs = BTreeMap::new();
 
         let routes = HttpRoute::all(conn).await.unwrap();
+        let error_pages = ErrorPage::all(conn).await.unwrap();
+        let auth_realms = AuthRealm::all(conn).await.unwrap();
 
         for mut route in routes {
             if route.enabled {
                 route.cleanup();
-                let router_name = format!("gui-http-{}-{}", route.id.unwrap(), route.name);
+                let mut config = TraefikConfig::new();
+                let router_name = route.router_name();
 
                 let mut host_rule = if route.host_regex {
                     format!("HostRegexp(`{}`)", route.host.trim())
@@ -119,50 +244,124 @@ impl HttpRoute {
                     format!("( {} )", hosts)
                 };
 
-                if let Some(prefix) = route.prefix {
+                if let Some(prefix) = &route.prefix {
                     host_rule = format!("({} && PathPrefix(`{}`))", host_rule, prefix);
                 }
 
-                config.routers.insert(
+                let mut middlewares = if route.skip_default_middlewares {
+                    Vec::new()
+                } else {
+                    app_config.default_middleware_names(false)
+                };
+                middlewares.extend(route.limits().add_to_config(&mut config, &router_name));
+                middlewares.extend(auth::route_middleware(
+                    &auth_realms,
+                    route.auth_realm.as_deref(),
+                ));
+                if route.forward_auth {
+                    middlewares.push(app_config.forward_auth_name());
+                }
+
+                let service = if route.maintenance {
+                    let (service, maintenance_middlewares) = maintenance::add_to_config(
+                        &mut config,
+                        &router_name,
+                        "http",
+                        route.id.unwrap_or_default(),
+                        app_config,
+                    );
+                    middlewares.extend(maintenance_middlewares);
+                    service
+                } else {
+                    middlewares.extend(error_pages::route_middlewares(
+                        &error_pages,
+                        route.error_page.as_deref(),
+                        app_config,
+                    ));
+                    middlewares.extend(
+                        route
+                            .path_rewrite()
+                            .add_to_config(&mut config, &router_name),
+                    );
+                    route
+                        .failover()
+                        .add_to_config(&mut config, &router_name, &router_name)
+                };
+
+                config.http.routers.insert(
                     router_name.clone(),
                     HttpRouter {
                         priority: route.priority,
-                        service: router_name.clone(),
+                        service,
                         rule: host_rule,
-                        middlewares: Vec::new(),
+                        middlewares,
                         tls: None,
                     },
                 );
 
-                config.services.insert(
-                    router_name,
+                config.http.services.insert(
+                    router_name.clone(),
                     HttpService {
-                        load_balancer: HttpLoadBalancer {
+                        load_balancer: Some(HttpLoadBalancer {
+                            health_check: route.failover().health_check(),
                             servers: vec![{ HttpServer { url: route.target } }],
-                        },
+                        }),
+                        ..Default::default()
                     },
                 );
+
+                configs.insert(router_name, config);
             }
         }
 
-        config
+        configs
     }
 }
 
 #[derive(Serialize)]
 struct Http {
     flash: Option<(String, String)>,
-    routes: Vec<HttpRoute>,
+    routes: Vec<RouteView<HttpRoute>>,
     edit: Option<i32>,
+    status_error: Option<String>,
+    error_pages: Vec<String>,
+    auth_realms: Vec<String>,
+    limit_sources: [&'static str; 3],
 }
 
 impl Http {
-    pub async fn raw(conn: &DbConn, flash: Option<(String, String)>, edit: Option<i32>) -> Self {
+    pub async fn raw(
+        conn: &DbConn,
+        config: &Config,
+        flash: Option<(String, String)>,
+        edit: Option<i32>,
+    ) -> Self {
+        let (status, status_error) = match status::load(config).await {
+            Ok(status) => (status, None),
+            Err(e) => (None, Some(e)),
+        };
+
+        let mut checks = RouteCheck::for_kind("http", conn).await.unwrap_or_default();
+
+        let error_pages = ErrorPage::names(conn).await.unwrap_or_default();
+        let auth_realms = AuthRealm::names(conn).await.unwrap_or_default();
+
         match HttpRoute::all(conn).await {
             Ok(routes) => Self {
                 flash,
-                routes,
+                routes: routes
+                    .into_iter()
+                    .map(|route| {
+                        let router_name = route.router_name();
+                        let check = route.id.and_then(|id| checks.remove(&id));
+                        RouteView::new(route, &router_name, status.as_ref(), check)
+                    })
+                    .collect(),
                 edit,
+                status_error,
+                error_pages,
+                auth_realms,
+                limit_sources: limits::SOURCES,
             },
             Err(e) => {
                 error!("DB error loading HTTP routes: {}", e);
@@ -170,6 +369,10 @@ impl Http {
                     flash: Some(("error".into(), e.to_string())),
                     routes: Vec::new(),
                     edit: None,
+                    status_error,
+                    error_pages,
+                    auth_realms,
+                    limit_sources: limits::SOURCES,
                 }
             }
         }
@@ -177,9 +380,17 @@ impl Http {
 }
 
 #[get("/http?<edit>")]
-pub async fn index(edit: Option<i32>, flash: Option<FlashMessage<'_>>, conn: DbConn) -> Template {
+pub async fn index(
+    edit: Option<i32>,
+    flash: Option<FlashMessage<'_>>,
+    conn: DbConn,
+    config: &State<ConfigState>,
+) -> Template {
     let flash = flash.map(FlashMessage::into_inner);
-    Template::render("http", Http::raw(&conn, flash, edit).await)
+    Template::render(
+        "http",
+        Http::raw(&conn, &config.config(), flash, edit).await,
+    )
 }
 
 #[post("/http", data = "<route_form>")]
@@ -188,9 +399,24 @@ pub async fn create(
     conn: DbConn,
     config: &State<ConfigState>,
 ) -> Flash<Redirect> {
-    let route = route_form.into_inner();
+    let mut route = route_form.into_inner();
+    route.cleanup();
 
     // TODO: validate
+    if let Err(e) = route
+        .path_rewrite()
+        .validate()
+        .and_then(|_| route.limits().validate())
+        .and_then(|_| route.failover().validate())
+    {
+        return Flash::error(Redirect::to("/http"), e);
+    }
+    if route.forward_auth && config.config().forward_auth_address.is_empty() {
+        return Flash::error(
+            Redirect::to("/http"),
+            "Set the forward auth address on the config page first",
+        );
+    }
 
     if let Err(e) = HttpRoute::insert(route, &conn).await {
         Flash::error(Redirect::to("/http"), e.to_string())
@@ -209,7 +435,23 @@ pub async fn update(
 ) -> Flash<Redirect> {
     // TODO: validate
 
-    let route = route_form.into_inner();
+    let mut route = route_form.into_inner();
+    route.cleanup();
+    if let Err(e) = route
+        .path_rewrite()
+        .validate()
+        .and_then(|_| route.limits().validate())
+        .and_then(|_| route.failover().validate())
+    {
+        return Flash::error(Redirect::to(format!("/http?edit={}", id)), e);
+    }
+    if route.forward_auth && config.config().forward_auth_address.is_empty() {
+        return Flash::error(
+            Redirect::to(format!("/http?edit={}", id)),
+            "Set the forward auth address on the config page first",
+        );
+    }
+
     if let Err(e) = HttpRoute::update(id, route, &conn).await {
         Flash::error(Redirect::to("/http"), e.to_string())
     } else {
@@ -234,6 +476,30 @@ pub async fn enable(
     }
 }
 
+#[post("/http/<id>/maintenance", data = "<maintenance>")]
+pub async fn set_maintenance(
+    id: i32,
+    maintenance: Form<bool>,
+    conn: DbConn,
+    config: &State<ConfigState>,
+) -> Flash<Redirect> {
+    let maintenance = maintenance.into_inner();
+    let config = config.config();
+    if maintenance && config.gui_url.trim().is_empty() {
+        return Flash::error(
+            Redirect::to("/http"),
+            "Set the GUI URL on the config page to use the maintenance mode",
+        );
+    }
+
+    if let Err(e) = HttpRoute::set_maintenance(id, maintenance, &conn).await {
+        Flash::error(Redirect::to("/http"), e.to_string())
+    } else {
+        export_traefik_config(&conn, &config).await;
+        Flash::success(Redirect::to("/http"), "Route updated")
+    }
+}
+
 #[post("/http/<id>/delete", data = "<confirm>")]
 pub async fn delete(
     id: i32,
@@ -274,6 +540,30 @@ pub async fn to_https(
                     target: route.target,
                     https_redirect: false,
                     allow_http_acme: false,
+                    cert_resolver: None,
+                    tls_domains: None,
+                    tls_options: None,
+                    maintenance: route.maintenance,
+                    maintenance_html: route.maintenance_html,
+                    error_page: route.error_page,
+                    skip_default_middlewares: route.skip_default_middlewares,
+                    header_profile: None,
+                    strip_prefix: route.strip_prefix,
+                    add_prefix: route.add_prefix,
+                    path_regex: route.path_regex,
+                    path_replacement: route.path_replacement,
+                    auth_realm: route.auth_realm,
+                    forward_auth: route.forward_auth,
+                    rate_limit_average: route.rate_limit_average,
+                    rate_limit_burst: route.rate_limit_burst,
+                    rate_limit_period: route.rate_limit_period,
+                    limit_source: route.limit_source,
+                    limit_header: route.limit_header,
+                    in_flight_requests: route.in_flight_requests,
+                    mirrors: None,
+                    fallback_target: route.fallback_target,
+                    health_check_path: route.health_check_path,
+                    health_check_interval: route.health_check_interval,
                 };
 
                 if let Err(e) = HttpsRoute::insert(new_route, &conn).await {
@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 use diesel::{ExpressionMethods, QueryDsl, QueryResult, RunQueryDsl};
 use itertools::Itertools;
 use rocket::{
@@ -7,22 +9,36 @@ use rocket::{
     State,
 };
 use rocket_dyn_templates::Template;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 use crate::{
+    auth::{self, AuthRealm},
+    check::RouteCheck,
     config::{Config, ConfigState},
+    error_pages::{self, ErrorPage},
     export_traefik_config,
+    failover::Failover,
+    headers::{self, HeaderProfile},
     http::HttpRoute,
+    limits::{self, RouteLimits},
+    maintenance, mirroring,
+    rewrite::PathRewrite,
     schema::https_routes::{self, dsl},
-    traefik::{HttpConfig, HttpLoadBalancer, HttpRouter, HttpServer, HttpService, HttpTls},
+    status::{self, RouteView},
+    tls_options::TlsOption,
+    traefik::{
+        HttpLoadBalancer, HttpRouter, HttpServer, HttpService, HttpTls, TlsDomain, TraefikConfig,
+    },
     DbConn, ACME_PATH,
 };
 
-#[derive(Serialize, Queryable, Insertable, AsChangeset, FromForm, Clone, Debug)]
+/// Resolver value of routes that use the default certificate of traefik instead of a resolver
+pub const NO_CERT_RESOLVER: &str = "none";
+
+#[derive(Serialize, Deserialize, Queryable, Insertable, AsChangeset, FromForm, Clone, Debug)]
 #[serde(crate = "rocket::serde")]
-#[diesel(table_name = https_routes)]
+#[diesel(table_name = https_routes, treat_none_as_null = true)]
 pub struct HttpsRoute {
-    #[serde(skip_deserializing)]
     pub id: Option<i32>,
     pub enabled: bool,
     pub name: String,
@@ -33,6 +49,72 @@ pub struct HttpsRoute {
     pub prefix: Option<String>,
     pub https_redirect: bool,
     pub allow_http_acme: bool,
+    /// `None` uses the default resolver from the config, see [`NO_CERT_RESOLVER`]
+    pub cert_resolver: Option<String>,
+    /// Comma separated domains of the certificate, the first one is the main domain
+    pub tls_domains: Option<String>,
+    /// Name of the TLS options profile, `None` uses the `default` profile
+    pub tls_options: Option<String>,
+    /// Serve the maintenance page of the GUI instead of the target
+    #[serde(default)]
+    pub maintenance: bool,
+    /// Custom HTML of the maintenance page, `None` uses the built-in page
+    #[serde(default)]
+    pub maintenance_html: Option<String>,
+    /// Name of an error page used in addition to the global ones
+    #[serde(default)]
+    pub error_page: Option<String>,
+    /// Don't apply the default middlewares from the config
+    #[serde(default)]
+    pub skip_default_middlewares: bool,
+    /// Name of the security header profile
+    #[serde(default)]
+    pub header_profile: Option<String>,
+    /// Remove the path prefix before forwarding to the target
+    #[serde(default)]
+    pub strip_prefix: bool,
+    /// Prefix added to the path before forwarding to the target
+    #[serde(default)]
+    pub add_prefix: Option<String>,
+    /// Regex matching the path, replaced with `path_replacement`
+    #[serde(default)]
+    pub path_regex: Option<String>,
+    #[serde(default)]
+    pub path_replacement: Option<String>,
+    /// Name of the realm whose users can access the route
+    #[serde(default)]
+    pub auth_realm: Option<String>,
+    /// Check the requests with the forward auth server from the config
+    #[serde(default)]
+    pub forward_auth: bool,
+    /// Requests per period on average, `None` disables the rate limit
+    #[serde(default)]
+    pub rate_limit_average: Option<i32>,
+    #[serde(default)]
+    pub rate_limit_burst: Option<i32>,
+    /// Seconds, see [`crate::limits::RouteLimits::rate_limit_period`]
+    #[serde(default)]
+    pub rate_limit_period: Option<i32>,
+    /// What the limits group the requests by, see [`crate::limits::SOURCES`]
+    #[serde(default)]
+    pub limit_source: Option<String>,
+    #[serde(default)]
+    pub limit_header: Option<String>,
+    /// Requests processed at the same time, `None` disables the limit
+    #[serde(default)]
+    pub in_flight_requests: Option<i32>,
+    /// Targets receiving a copy of the requests, one `target percent` per line
+    #[serde(default)]
+    pub mirrors: Option<String>,
+    /// Target used while the health check of the route target fails
+    #[serde(default)]
+    pub fallback_target: Option<String>,
+    /// Path checked on the route target, `None` disables the health check
+    #[serde(default)]
+    pub health_check_path: Option<String>,
+    /// Seconds between the health checks
+    #[serde(default)]
+    pub health_check_interval: Option<i32>,
 }
 
 impl HttpsRoute {
@@ -73,6 +155,7 @@ impl HttpsRoute {
     }
 
     pub async fn delete(id: i32, conn: &DbConn) -> QueryResult<usize> {
+        RouteCheck::delete("https", id, conn).await?;
         conn.run(move |c| {
             diesel::delete(https_routes::table)
                 .filter(https_routes::id.eq(id))
@@ -91,18 +174,114 @@ impl HttpsRoute {
         .await
     }
 
+    pub async fn set_maintenance(id: i32, maintenance: bool, conn: &DbConn) -> QueryResult<usize> {
+        conn.run(move |c| {
+            diesel::update(https_routes::table)
+                .filter(https_routes::id.eq(id))
+                .set(https_routes::maintenance.eq(maintenance))
+                .execute(c)
+        })
+        .await
+    }
+
+    pub fn router_name(&self) -> String {
+        format!("gui-https-{}-{}", self.id.unwrap_or_default(), self.name)
+    }
+
+    pub fn path_rewrite(&self) -> PathRewrite<'_> {
+        PathRewrite {
+            prefix: self.prefix.as_deref(),
+            strip_prefix: self.strip_prefix,
+            add_prefix: self.add_prefix.as_deref(),
+            path_regex: self.path_regex.as_deref(),
+            path_replacement: self.path_replacement.as_deref(),
+        }
+    }
+
+    pub fn limits(&self) -> RouteLimits<'_> {
+        RouteLimits {
+            rate_limit_average: self.rate_limit_average,
+            rate_limit_burst: self.rate_limit_burst,
+            rate_limit_period: self.rate_limit_period,
+            limit_source: self.limit_source.as_deref(),
+            limit_header: self.limit_header.as_deref(),
+            in_flight_requests: self.in_flight_requests,
+        }
+    }
+
+    pub fn failover(&self) -> Failover<'_> {
+        Failover {
+            fallback_target: self.fallback_target.as_deref(),
+            health_check_path: self.health_check_path.as_deref(),
+            health_check_interval: self.health_check_interval,
+        }
+    }
+
     pub fn cleanup(&mut self) {
-        if let Some(prefix) = &self.prefix {
-            if prefix.trim().is_empty() {
-                self.prefix = None;
+        for field in [
+            &mut self.prefix,
+            &mut self.cert_resolver,
+            &mut self.tls_domains,
+            &mut self.tls_options,
+            &mut self.maintenance_html,
+            &mut self.error_page,
+            &mut self.add_prefix,
+            &mut self.path_regex,
+            &mut self.path_replacement,
+            &mut self.auth_realm,
+            &mut self.limit_source,
+            &mut self.limit_header,
+            &mut self.mirrors,
+            &mut self.fallback_target,
+            &mut self.health_check_path,
+            &mut self.header_profile,
+        ] {
+            if field.as_ref().is_some_and(|value| value.trim().is_empty()) {
+                *field = None;
             }
         }
     }
 
-    pub async fn generate_traefik_config(conn: &DbConn, config: &Config) -> HttpConfig {
-        let mut traefik_config = HttpConfig::new();
+    pub fn tls(&self, default_resolver: &Option<String>) -> HttpTls {
+        let cert_resolver = match self.cert_resolver.as_deref().map(str::trim) {
+            None => default_resolver.clone(),
+            Some(NO_CERT_RESOLVER) => None,
+            Some(resolver) => Some(resolver.to_string()),
+        };
+
+        let mut domains = self
+            .tls_domains
+            .iter()
+            .flat_map(|domains| domains.split(','))
+            .map(str::trim)
+            .filter(|domain| !domain.is_empty())
+            .map(String::from);
+
+        let domains = match domains.next() {
+            Some(main) => vec![TlsDomain {
+                main,
+                sans: domains.collect(),
+            }],
+            None => Vec::new(),
+        };
+
+        HttpTls {
+            cert_resolver,
+            domains,
+            options: self.tls_options.clone(),
+        }
+    }
+
+    pub async fn generate_traefik_config(
+        conn: &DbConn,
+        config: &Config,
+    ) -> BTreeMap<String, TraefikConfig> {
+        let mut configs = BTreeMap::new();
 
         let routes = HttpsRoute::all(conn).await.unwrap();
+        let error_pages = ErrorPage::all(conn).await.unwrap();
+        let header_profiles = HeaderProfile::all(conn).await.unwrap();
+        let auth_realms = AuthRealm::all(conn).await.unwrap();
 
         let acme_provider = if config.acme_provider_name.is_empty() {
             None
@@ -113,7 +292,8 @@ impl HttpsRoute {
         for mut route in routes {
             if route.enabled {
                 route.cleanup();
-                let router_name = format!("gui-https-{}-{}", route.id.unwrap(), route.name);
+                let mut traefik_config = TraefikConfig::new();
+                let router_name = route.router_name();
 
                 let base_rule = if route.host_regex {
                     format!("HostRegexp(`{}`)", route.host.trim())
@@ -128,7 +308,7 @@ impl HttpsRoute {
                     format!("( {} )", hosts)
                 };
 
-                let host_rule = if let Some(prefix) = route.prefix {
+                let host_rule = if let Some(prefix) = &route.prefix {
                     format!("({} && PathPrefix(`{}`))", base_rule, prefix)
                 } else {
                     base_rule.clone()
@@ -137,7 +317,7 @@ impl HttpsRoute {
                 if route.https_redirect {
                     let redirect_router_name = format!("{}-redirect", router_name);
 
-                    traefik_config.routers.insert(
+                    traefik_config.http.routers.insert(
                         redirect_router_name,
                         HttpRouter {
                             rule: host_rule.clone(),
@@ -153,7 +333,7 @@ impl HttpsRoute {
                     let acme_router_name = format!("{}-acme", router_name);
                     let acme_rule = format!("({} && PathPrefix(`{}`))", base_rule, ACME_PATH);
 
-                    traefik_config.routers.insert(
+                    traefik_config.http.routers.insert(
                         acme_router_name,
                         HttpRouter {
                             rule: acme_rule,
@@ -165,48 +345,159 @@ impl HttpsRoute {
                     );
                 }
 
-                traefik_config.routers.insert(
+                let mut middlewares = if route.skip_default_middlewares {
+                    Vec::new()
+                } else {
+                    config.default_middleware_names(true)
+                };
+                middlewares.extend(
+                    route
+                        .limits()
+                        .add_to_config(&mut traefik_config, &router_name),
+                );
+                middlewares.extend(headers::route_middleware(
+                    &header_profiles,
+                    route.header_profile.as_deref(),
+                ));
+                middlewares.extend(auth::route_middleware(
+                    &auth_realms,
+                    route.auth_realm.as_deref(),
+                ));
+                if route.forward_auth {
+                    middlewares.push(config.forward_auth_name());
+                }
+
+                let service = if route.maintenance {
+                    let (service, maintenance_middlewares) = maintenance::add_to_config(
+                        &mut traefik_config,
+                        &router_name,
+                        "https",
+                        route.id.unwrap_or_default(),
+                        config,
+                    );
+                    middlewares.extend(maintenance_middlewares);
+                    service
+                } else {
+                    middlewares.extend(error_pages::route_middlewares(
+                        &error_pages,
+                        route.error_page.as_deref(),
+                        config,
+                    ));
+                    middlewares.extend(
+                        route
+                            .path_rewrite()
+                            .add_to_config(&mut traefik_config, &router_name),
+                    );
+
+                    let service = route.failover().add_to_config(
+                        &mut traefik_config,
+                        &router_name,
+                        &router_name,
+                    );
+
+                    // validated when saving
+                    let mirrors = route
+                        .mirrors
+                        .as_deref()
+                        .map(mirroring::parse)
+                        .and_then(Result::ok)
+                        .unwrap_or_default();
+                    if mirrors.is_empty() {
+                        service
+                    } else {
+                        mirroring::add_to_config(
+                            &mut traefik_config,
+                            &router_name,
+                            &service,
+                            mirrors,
+                        )
+                    }
+                };
+
+                traefik_config.http.routers.insert(
                     router_name.clone(),
                     HttpRouter {
                         priority: route.priority,
-                        service: router_name.clone(),
+                        service,
                         rule: host_rule,
-                        middlewares: Vec::new(),
-                        tls: Some(HttpTls {
-                            cert_resolver: acme_provider.clone(),
-                        }),
+                        middlewares,
+                        tls: Some(route.tls(&acme_provider)),
                     },
                 );
 
-                traefik_config.services.insert(
-                    router_name,
+                traefik_config.http.services.insert(
+                    router_name.clone(),
                     HttpService {
-                        load_balancer: HttpLoadBalancer {
+                        load_balancer: Some(HttpLoadBalancer {
+                            health_check: route.failover().health_check(),
                             servers: vec![{ HttpServer { url: route.target } }],
-                        },
+                        }),
+                        ..Default::default()
                     },
                 );
+
+                configs.insert(router_name, traefik_config);
             }
         }
 
-        traefik_config
+        configs
     }
 }
 
 #[derive(Serialize)]
 struct Https {
     flash: Option<(String, String)>,
-    routes: Vec<HttpsRoute>,
+    routes: Vec<RouteView<HttpsRoute>>,
     edit: Option<i32>,
+    status_error: Option<String>,
+    resolvers: Vec<String>,
+    tls_options: Vec<String>,
+    error_pages: Vec<String>,
+    header_profiles: Vec<String>,
+    auth_realms: Vec<String>,
+    limit_sources: [&'static str; 3],
 }
 
 impl Https {
-    pub async fn raw(conn: &DbConn, flash: Option<(String, String)>, edit: Option<i32>) -> Self {
+    pub async fn raw(
+        conn: &DbConn,
+        config: &Config,
+        flash: Option<(String, String)>,
+        edit: Option<i32>,
+    ) -> Self {
+        let (status, status_error) = match status::load(config).await {
+            Ok(status) => (status, None),
+            Err(e) => (None, Some(e)),
+        };
+
+        let mut checks = RouteCheck::for_kind("https", conn)
+            .await
+            .unwrap_or_default();
+
+        let tls_options = TlsOption::names(conn).await.unwrap_or_default();
+        let error_pages = ErrorPage::names(conn).await.unwrap_or_default();
+        let auth_realms = AuthRealm::names(conn).await.unwrap_or_default();
+        let header_profiles = HeaderProfile::names(conn).await.unwrap_or_default();
+
         match HttpsRoute::all(conn).await {
             Ok(routes) => Self {
                 flash,
-                routes,
+                routes: routes
+                    .into_iter()
+                    .map(|route| {
+                        let router_name = route.router_name();
+                        let check = route.id.and_then(|id| checks.remove(&id));
+                        RouteView::new(route, &router_name, status.as_ref(), check)
+                    })
+                    .collect(),
                 edit,
+                status_error,
+                resolvers: config.resolvers(),
+                tls_options,
+                error_pages,
+                header_profiles,
+                auth_realms,
+                limit_sources: limits::SOURCES,
             },
             Err(e) => {
                 error!("DB error loading HTTP routes: {}", e);
@@ -214,6 +505,13 @@ impl Https {
                     flash: Some(("error".into(), e.to_string())),
                     routes: Vec::new(),
                     edit: None,
+                    status_error,
+                    resolvers: config.resolvers(),
+                    tls_options,
+                    error_pages,
+                    header_profiles,
+                    auth_realms,
+                    limit_sources: limits::SOURCES,
                 }
             }
         }
@@ -221,9 +519,17 @@ impl Https {
 }
 
 #[get("/https?<edit>")]
-pub async fn index(edit: Option<i32>, flash: Option<FlashMessage<'_>>, conn: DbConn) -> Template {
+pub async fn index(
+    edit: Option<i32>,
+    flash: Option<FlashMessage<'_>>,
+    conn: DbConn,
+    config: &State<ConfigState>,
+) -> Template {
     let flash = flash.map(FlashMessage::into_inner);
-    Template::render("https", Https::raw(&conn, flash, edit).await)
+    Template::render(
+        "https",
+        Https::raw(&conn, &config.config(), flash, edit).await,
+    )
 }
 
 #[post("/https", data = "<route_form>")]
@@ -232,9 +538,25 @@ pub async fn create(
     conn: DbConn,
     config: &State<ConfigState>,
 ) -> Flash<Redirect> {
-    let route = route_form.into_inner();
+    let mut route = route_form.into_inner();
+    route.cleanup();
 
     // TODO: validate
+    if let Err(e) = route
+        .path_rewrite()
+        .validate()
+        .and_then(|_| route.limits().validate())
+        .and_then(|_| mirroring::parse(route.mirrors.as_deref().unwrap_or_default()).map(|_| ()))
+        .and_then(|_| route.failover().validate())
+    {
+        return Flash::error(Redirect::to("/https"), e);
+    }
+    if route.forward_auth && config.config().forward_auth_address.is_empty() {
+        return Flash::error(
+            Redirect::to("/https"),
+            "Set the forward auth address on the config page first",
+        );
+    }
 
     if let Err(e) = HttpsRoute::insert(route, &conn).await {
         Flash::error(Redirect::to("/https"), e.to_string())
@@ -253,7 +575,24 @@ pub async fn update(
 ) -> Flash<Redirect> {
     // TODO: validate
 
-    let route = route_form.into_inner();
+    let mut route = route_form.into_inner();
+    route.cleanup();
+    if let Err(e) = route
+        .path_rewrite()
+        .validate()
+        .and_then(|_| route.limits().validate())
+        .and_then(|_| mirroring::parse(route.mirrors.as_deref().unwrap_or_default()).map(|_| ()))
+        .and_then(|_| route.failover().validate())
+    {
+        return Flash::error(Redirect::to(format!("/https?edit={}", id)), e);
+    }
+    if route.forward_auth && config.config().forward_auth_address.is_empty() {
+        return Flash::error(
+            Redirect::to(format!("/https?edit={}", id)),
+            "Set the forward auth address on the config page first",
+        );
+    }
+
     if let Err(e) = HttpsRoute::update(id, route, &conn).await {
         Flash::error(Redirect::to("/https"), e.to_string())
     } else {
@@ -278,6 +617,30 @@ pub async fn enable(
     }
 }
 
+#[post("/https/<id>/maintenance", data = "<maintenance>")]
+pub async fn set_maintenance(
+    id: i32,
+    maintenance: Form<bool>,
+    conn: DbConn,
+    config: &State<ConfigState>,
+) -> Flash<Redirect> {
+    let maintenance = maintenance.into_inner();
+    let config = config.config();
+    if maintenance && config.gui_url.trim().is_empty() {
+        return Flash::error(
+            Redirect::to("/https"),
+            "Set the GUI URL on the config page to use the maintenance mode",
+        );
+    }
+
+    if let Err(e) = HttpsRoute::set_maintenance(id, maintenance, &conn).await {
+        Flash::error(Redirect::to("/https"), e.to_string())
+    } else {
+        export_traefik_config(&conn, &config).await;
+        Flash::success(Redirect::to("/https"), "Route updated")
+    }
+}
+
 #[post("/https/<id>/delete", data = "<confirm>")]
 pub async fn delete(
     id: i32,
@@ -316,6 +679,25 @@ pub async fn to_http(
                     prefix: route.prefix,
                     priority: route.priority,
                     target: route.target,
+                    maintenance: route.maintenance,
+                    maintenance_html: route.maintenance_html,
+                    error_page: route.error_page,
+                    skip_default_middlewares: route.skip_default_middlewares,
+                    strip_prefix: route.strip_prefix,
+                    add_prefix: route.add_prefix,
+                    path_regex: route.path_regex,
+                    path_replacement: route.path_replacement,
+                    auth_realm: route.auth_realm,
+                    forward_auth: route.forward_auth,
+                    rate_limit_average: route.rate_limit_average,
+                    rate_limit_burst: route.rate_limit_burst,
+                    rate_limit_period: route.rate_limit_period,
+                    limit_source: route.limit_source,
+                    limit_header: route.limit_header,
+                    in_flight_requests: route.in_flight_requests,
+                    fallback_target: route.fallback_target,
+                    health_check_path: route.health_check_path,
+                    health_check_interval: route.health_check_interval,
                 };
 
                 if let Err(e) = HttpRoute::insert(new_route, &conn).await {
@@ -0,0 +1,802 @@
+//! Renders the generated config as manifests for the traefik Kubernetes CRD provider.
+//!
+//! Targets live outside the cluster, so every traefik service becomes a Kubernetes `Service`:
+//! hostnames use an `ExternalName` service, IP addresses a selector-less service with manual `Endpoints`.
+
+use std::{collections::BTreeMap, net::IpAddr};
+
+use md5::{Digest, Md5};
+use rocket::{http::ContentType, State};
+use serde::Serialize;
+
+use crate::{
+    config::ConfigState,
+    generate_traefik_config,
+    traefik::{HttpMiddleware, ProxyProtocol, TlsDomain, TraefikConfig},
+    x509::CertificateInfo,
+    DbConn, Download,
+};
+
+const TRAEFIK_API_VERSION: &str = "traefik.io/v1alpha1";
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Manifest<T: Serialize> {
+    api_version: &'static str,
+    kind: &'static str,
+    metadata: Metadata,
+    #[serde(flatten)]
+    body: T,
+}
+
+#[derive(Serialize)]
+struct Metadata {
+    name: String,
+    namespace: String,
+}
+
+#[derive(Serialize)]
+struct Spec<T: Serialize> {
+    spec: T,
+}
+
+#[derive(Serialize)]
+struct IngressRouteSpec {
+    routes: Vec<IngressRouteRule>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tls: Option<IngressRouteTls>,
+}
+
+#[derive(Serialize)]
+struct IngressRouteRule {
+    #[serde(rename = "match")]
+    rule: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    kind: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    priority: Option<i32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    middlewares: Vec<NameRef>,
+    services: Vec<ServiceRef>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct IngressRouteTls {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cert_resolver: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    domains: Vec<TlsDomain>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    options: Option<NameRef>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    passthrough: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    secret_name: Option<String>,
+}
+
+#[derive(Serialize)]
+struct NameRef {
+    name: String,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct ServiceRef {
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    kind: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    port: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scheme: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    servers_transport: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    proxy_protocol: Option<ProxyProtocol>,
+}
+
+#[derive(Serialize)]
+struct ErrorsMiddlewareSpec<'a> {
+    errors: ErrorsSpec<'a>,
+}
+
+#[derive(Serialize)]
+struct ErrorsSpec<'a> {
+    status: &'a Vec<String>,
+    query: &'a String,
+    service: ServiceRef,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AuthMiddlewareSpec<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    basic_auth: Option<AuthSpec<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    digest_auth: Option<AuthSpec<'a>>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AuthSpec<'a> {
+    secret: String,
+    realm: &'a String,
+    remove_header: bool,
+}
+
+#[derive(Serialize)]
+struct MirroringServiceSpec {
+    mirroring: MirroringSpec,
+}
+
+#[derive(Serialize)]
+struct MirroringSpec {
+    #[serde(flatten)]
+    service: ServiceRef,
+    mirrors: Vec<MirrorSpec>,
+}
+
+#[derive(Serialize)]
+struct MirrorSpec {
+    #[serde(flatten)]
+    service: ServiceRef,
+    percent: u8,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ServersTransportSpec {
+    server_name: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TlsOptionSpec<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    min_version: Option<&'a String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    cipher_suites: &'a Vec<String>,
+    sni_strict: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    client_auth: Option<ClientAuthSpec>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ClientAuthSpec {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    secret_names: Vec<String>,
+    client_auth_type: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SecretBody {
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    secret_type: Option<&'static str>,
+    string_data: BTreeMap<&'static str, String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TlsStoreSpec {
+    certificates: Vec<SecretRef>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    default_certificate: Option<SecretRef>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SecretRef {
+    secret_name: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ServiceSpec {
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    service_type: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    external_name: Option<String>,
+    ports: Vec<ServicePort>,
+}
+
+#[derive(Serialize)]
+struct ServicePort {
+    port: u16,
+}
+
+#[derive(Serialize)]
+struct EndpointsBody {
+    subsets: Vec<EndpointSubset>,
+}
+
+#[derive(Serialize)]
+struct EndpointSubset {
+    addresses: Vec<EndpointAddress>,
+    ports: Vec<ServicePort>,
+}
+
+#[derive(Serialize)]
+struct EndpointAddress {
+    ip: String,
+}
+
+/// A backend outside the cluster
+struct Target {
+    scheme: Option<String>,
+    host: String,
+    port: u16,
+}
+
+impl Target {
+    /// Parses `scheme://host:port/path` for HTTP and `host:port` for TCP services
+    fn parse(target: &str, default_port: u16) -> Self {
+        let (scheme, rest) = match target.split_once("://") {
+            Some((scheme, rest)) => (Some(scheme.to_lowercase()), rest),
+            None => (None, target),
+        };
+
+        let authority = rest.split('/').next().unwrap_or_default();
+        let default_port = match scheme.as_deref() {
+            Some("https") => 443,
+            Some("http") => 80,
+            _ => default_port,
+        };
+
+        // IPv6 addresses are written as `[::1]:port`
+        let (host, port) = if let Some(rest) = authority.strip_prefix('[') {
+            let (host, port) = rest.split_once(']').unwrap_or((rest, ""));
+            (host.to_string(), port.trim_start_matches(':').parse().ok())
+        } else {
+            match authority.rsplit_once(':') {
+                Some((host, port)) => (host.to_string(), port.parse().ok()),
+                None => (authority.to_string(), None),
+            }
+        };
+
+        Self {
+            scheme,
+            host,
+            port: port.unwrap_or(default_port),
+        }
+    }
+}
+
+/// Kubernetes names are limited to 63 lowercase alphanumerics and dashes.
+/// Longer names are cut and get a short hash of the full name, so they stay unique.
+fn resource_name(name: &str) -> String {
+    let sanitized = name
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect::<String>();
+
+    if sanitized.len() <= 63 {
+        return sanitized.trim_matches('-').to_string();
+    }
+
+    let hash = Md5::digest(name);
+    let hash: String = hash[..4]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    let prefix = sanitized[..54].trim_matches('-');
+    format!("{}-{}", prefix, hash)
+}
+
+struct Manifests {
+    namespace: String,
+    documents: Vec<String>,
+}
+
+impl Manifests {
+    fn push<T: Serialize>(
+        &mut self,
+        kind: &'static str,
+        api_version: &'static str,
+        name: &str,
+        body: T,
+    ) {
+        let manifest = Manifest {
+            api_version,
+            kind,
+            metadata: Metadata {
+                name: resource_name(name),
+                namespace: self.namespace.clone(),
+            },
+            body,
+        };
+        self.documents
+            .push(serde_yaml::to_string(&manifest).unwrap());
+    }
+
+    /// Adds the Kubernetes service for an external target and returns the reference for a route
+    fn push_service(&mut self, name: &str, target: &Target) -> ServiceRef {
+        match target.host.parse::<IpAddr>() {
+            Ok(ip) => {
+                self.push(
+                    "Service",
+                    "v1",
+                    name,
+                    Spec {
+                        spec: ServiceSpec {
+                            service_type: None,
+                            external_name: None,
+                            ports: vec![ServicePort { port: target.port }],
+                        },
+                    },
+                );
+                self.push(
+                    "Endpoints",
+                    "v1",
+                    name,
+                    EndpointsBody {
+                        subsets: vec![EndpointSubset {
+                            addresses: vec![EndpointAddress { ip: ip.to_string() }],
+                            ports: vec![ServicePort { port: target.port }],
+                        }],
+                    },
+                );
+            }
+            Err(_) => {
+                self.push(
+                    "Service",
+                    "v1",
+                    name,
+                    Spec {
+                        spec: ServiceSpec {
+                            service_type: Some("ExternalName"),
+                            external_name: Some(target.host.clone()),
+                            ports: vec![ServicePort { port: target.port }],
+                        },
+                    },
+                );
+            }
+        }
+
+        let mut service = ServiceRef {
+            name: resource_name(name),
+            kind: None,
+            port: Some(target.port),
+            // http is the default and doesn't need to be set
+            scheme: target.scheme.clone().filter(|scheme| scheme == "https"),
+            servers_transport: None,
+            proxy_protocol: None,
+        };
+
+        if target.scheme.as_deref() == Some("https") {
+            let transport_name = format!("{}-transport", name);
+            self.push(
+                "ServersTransport",
+                TRAEFIK_API_VERSION,
+                &transport_name,
+                Spec {
+                    spec: ServersTransportSpec {
+                        server_name: target.host.clone(),
+                    },
+                },
+            );
+            service.servers_transport = Some(resource_name(&transport_name));
+        }
+
+        service
+    }
+
+    fn push_middleware(&mut self, name: &str, middleware: &HttpMiddleware) {
+        self.push(
+            "Middleware",
+            TRAEFIK_API_VERSION,
+            name,
+            Spec { spec: middleware },
+        );
+    }
+}
+
+/// The secret of the uploaded certificate covering all hosts of the rule.
+/// Rules without plain hosts, e.g. `HostRegexp`, fall back to the default certificate of the TLS store.
+fn certificate_secret(
+    rule: &str,
+    certificates: &[(String, Option<CertificateInfo>)],
+) -> Option<String> {
+    let hosts = ["Host(`", "HostSNI(`"]
+        .iter()
+        .flat_map(|matcher| rule.split(matcher).skip(1))
+        .filter_map(|rest| rest.split('`').next())
+        .collect::<Vec<_>>();
+    if hosts.is_empty() {
+        return None;
+    }
+
+    certificates
+        .iter()
+        .find(|(_, info)| {
+            info.as_ref()
+                .is_some_and(|info| hosts.iter().all(|host| info.covers(host)))
+        })
+        .map(|(secret_name, _)| secret_name.clone())
+}
+
+/// Services like `noop@internal` come from other providers and are referenced as `TraefikService`
+fn provider_service(name: &str) -> ServiceRef {
+    ServiceRef {
+        name: name.to_string(),
+        kind: Some("TraefikService"),
+        port: None,
+        scheme: None,
+        servers_transport: None,
+        proxy_protocol: None,
+    }
+}
+
+pub fn render(config: &TraefikConfig, namespace: &str) -> String {
+    let mut manifests = Manifests {
+        namespace: namespace.to_string(),
+        documents: Vec::new(),
+    };
+
+    // the certificate files are inline, see `export`
+    let mut certificates = Vec::new();
+    for certificate in &config.tls.certificates {
+        manifests.push(
+            "Secret",
+            "v1",
+            &certificate.name,
+            SecretBody {
+                secret_type: Some("kubernetes.io/tls"),
+                string_data: BTreeMap::from([
+                    ("tls.crt", certificate.cert_file.clone()),
+                    ("tls.key", certificate.key_file.clone()),
+                ]),
+            },
+        );
+        certificates.push((
+            resource_name(&certificate.name),
+            CertificateInfo::from_pem(certificate.cert_file.as_bytes()).ok(),
+        ));
+    }
+
+    if !certificates.is_empty() {
+        // traefik picks the certificate by SNI from the store, like the certificates of the file provider
+        let default_certificate = config
+            .tls
+            .stores
+            .get("default")
+            .and_then(|store| store.default_certificate.as_ref())
+            .map(|certificate| SecretRef {
+                secret_name: resource_name(&certificate.name),
+            });
+        manifests.push(
+            "TLSStore",
+            TRAEFIK_API_VERSION,
+            "default",
+            Spec {
+                spec: TlsStoreSpec {
+                    certificates: certificates
+                        .iter()
+                        .map(|(secret_name, _)| SecretRef {
+                            secret_name: secret_name.clone(),
+                        })
+                        .collect(),
+                    default_certificate,
+                },
+            },
+        );
+    }
+
+    // the CA files are inline, see `export`
+    for (name, options) in &config.tls.options {
+        let client_auth = options.client_auth.as_ref().map(|client_auth| {
+            let mut secret_names = Vec::new();
+            for (i, ca) in client_auth.ca_files.iter().enumerate() {
+                let secret_name = format!("{}-ca-{}", name, i);
+                manifests.push(
+                    "Secret",
+                    "v1",
+                    &secret_name,
+                    SecretBody {
+                        secret_type: None,
+                        string_data: BTreeMap::from([("tls.ca", ca.clone())]),
+                    },
+                );
+                secret_names.push(resource_name(&secret_name));
+            }
+
+            ClientAuthSpec {
+                secret_names,
+                client_auth_type: client_auth.client_auth_type.clone(),
+            }
+        });
+
+        manifests.push(
+            "TLSOption",
+            TRAEFIK_API_VERSION,
+            name,
+            Spec {
+                spec: TlsOptionSpec {
+                    min_version: options.min_version.as_ref(),
+                    cipher_suites: &options.cipher_suites,
+                    sni_strict: options.sni_strict,
+                    client_auth,
+                },
+            },
+        );
+    }
+
+    let mut http_services = BTreeMap::new();
+    for (name, service) in &config.http.services {
+        if let Some(server) = service
+            .load_balancer
+            .as_ref()
+            .and_then(|load_balancer| load_balancer.servers.first())
+        {
+            let target = Target::parse(&server.url, 80);
+            http_services.insert(name, manifests.push_service(name, &target));
+        }
+    }
+
+    // the CRD has no failover service, the routes use the main service without the fallback
+    for (name, service) in &config.http.services {
+        if let Some(failover) = &service.failover {
+            if let Some(service_ref) = http_services.get(&failover.service).cloned() {
+                http_services.insert(name, service_ref);
+            }
+        }
+    }
+
+    // the mirrored services have to be known first
+    for (name, service) in &config.http.services {
+        if let Some(mirroring) = &service.mirroring {
+            let service_ref = |name: &String| {
+                http_services
+                    .get(name)
+                    .cloned()
+                    .unwrap_or_else(|| provider_service(name))
+            };
+
+            manifests.push(
+                "TraefikService",
+                TRAEFIK_API_VERSION,
+                name,
+                Spec {
+                    spec: MirroringServiceSpec {
+                        mirroring: MirroringSpec {
+                            service: service_ref(&mirroring.service),
+                            mirrors: mirroring
+                                .mirrors
+                                .iter()
+                                .map(|mirror| MirrorSpec {
+                                    service: service_ref(&mirror.name),
+                                    percent: mirror.percent,
+                                })
+                                .collect(),
+                        },
+                    },
+                },
+            );
+            http_services.insert(
+                name,
+                ServiceRef {
+                    name: resource_name(name),
+                    ..provider_service(name)
+                },
+            );
+        }
+    }
+
+    for (name, middleware) in &config.http.middlewares {
+        let auth = middleware
+            .basic_auth
+            .as_ref()
+            .or(middleware.digest_auth.as_ref());
+
+        if let Some(errors) = &middleware.errors {
+            // the CRD references the error service like a route instead of by name
+            manifests.push(
+                "Middleware",
+                TRAEFIK_API_VERSION,
+                name,
+                Spec {
+                    spec: ErrorsMiddlewareSpec {
+                        errors: ErrorsSpec {
+                            status: &errors.status,
+                            query: &errors.query,
+                            service: http_services
+                                .get(&errors.service)
+                                .cloned()
+                                .unwrap_or_else(|| provider_service(&errors.service)),
+                        },
+                    },
+                },
+            );
+        } else if let Some(auth) = auth {
+            // the CRD reads the users from a secret
+            let secret_name = format!("{}-users", name);
+            manifests.push(
+                "Secret",
+                "v1",
+                &secret_name,
+                SecretBody {
+                    secret_type: None,
+                    string_data: BTreeMap::from([("users", auth.users.join("\n"))]),
+                },
+            );
+
+            let spec = AuthSpec {
+                secret: resource_name(&secret_name),
+                realm: &auth.realm,
+                remove_header: auth.remove_header,
+            };
+            let spec = if middleware.digest_auth.is_some() {
+                AuthMiddlewareSpec {
+                    basic_auth: None,
+                    digest_auth: Some(spec),
+                }
+            } else {
+                AuthMiddlewareSpec {
+                    basic_auth: Some(spec),
+                    digest_auth: None,
+                }
+            };
+            manifests.push("Middleware", TRAEFIK_API_VERSION, name, Spec { spec });
+        } else {
+            manifests.push_middleware(name, middleware);
+        }
+    }
+
+    for (name, router) in &config.http.routers {
+        let service = http_services
+            .get(&router.service)
+            .cloned()
+            .unwrap_or_else(|| provider_service(&router.service));
+
+        manifests.push(
+            "IngressRoute",
+            TRAEFIK_API_VERSION,
+            name,
+            Spec {
+                spec: IngressRouteSpec {
+                    routes: vec![IngressRouteRule {
+                        rule: router.rule.clone(),
+                        kind: Some("Rule"),
+                        priority: router.priority,
+                        middlewares: router
+                            .middlewares
+                            .iter()
+                            .map(|name| NameRef {
+                                name: if name.contains('@') {
+                                    name.clone()
+                                } else {
+                                    resource_name(name)
+                                },
+                            })
+                            .collect(),
+                        services: vec![service],
+                    }],
+                    tls: router.tls.as_ref().map(|tls| IngressRouteTls {
+                        cert_resolver: tls.cert_resolver.clone(),
+                        domains: tls.domains.clone(),
+                        options: tls.options.as_ref().map(|options| NameRef {
+                            name: resource_name(options),
+                        }),
+                        passthrough: None,
+                        secret_name: tls
+                            .cert_resolver
+                            .is_none()
+                            .then(|| certificate_secret(&router.rule, &certificates))
+                            .flatten(),
+                    }),
+                },
+            },
+        );
+    }
+
+    for (name, middleware) in &config.tcp.middlewares {
+        manifests.push(
+            "MiddlewareTCP",
+            TRAEFIK_API_VERSION,
+            name,
+            Spec { spec: middleware },
+        );
+    }
+
+    let mut tcp_services = BTreeMap::new();
+    for (name, service) in &config.tcp.services {
+        if let Some(server) = service.load_balancer.servers.first() {
+            let target = Target::parse(&server.address, 443);
+            let mut service_ref = manifests.push_service(name, &target);
+
+            // the CRD only supports the PROXY protocol on the service reference
+            service_ref.proxy_protocol = service.load_balancer.proxy_protocol.or_else(|| {
+                service
+                    .load_balancer
+                    .servers_transport
+                    .as_ref()
+                    .and_then(|transport| config.tcp.servers_transports.get(transport))
+                    .and_then(|transport| transport.proxy_protocol)
+            });
+
+            tcp_services.insert(name, service_ref);
+        }
+    }
+
+    for (name, router) in &config.tcp.routers {
+        let service = tcp_services
+            .get(&router.service)
+            .cloned()
+            .unwrap_or_else(|| provider_service(&router.service));
+
+        manifests.push(
+            "IngressRouteTCP",
+            TRAEFIK_API_VERSION,
+            name,
+            Spec {
+                spec: IngressRouteSpec {
+                    routes: vec![IngressRouteRule {
+                        rule: router.rule.clone(),
+                        kind: None,
+                        priority: router.priority,
+                        middlewares: router
+                            .middlewares
+                            .iter()
+                            .map(|name| NameRef {
+                                name: resource_name(name),
+                            })
+                            .collect(),
+                        services: vec![service],
+                    }],
+                    tls: router.tls.as_ref().map(|tls| IngressRouteTls {
+                        cert_resolver: tls.cert_resolver.clone(),
+                        domains: Vec::new(),
+                        options: tls.options.as_ref().map(|options| NameRef {
+                            name: resource_name(options),
+                        }),
+                        passthrough: Some(tls.passthrough),
+                        secret_name: (tls.cert_resolver.is_none() && !tls.passthrough)
+                            .then(|| certificate_secret(&router.rule, &certificates))
+                            .flatten(),
+                    }),
+                },
+            },
+        );
+    }
+
+    manifests.documents.join("---\n")
+}
+
+#[get("/kubernetes?<namespace>")]
+pub async fn export(namespace: Option<&str>, conn: DbConn, state: &State<ConfigState>) -> Download {
+    let namespace = namespace
+        .map(str::trim)
+        .filter(|namespace| !namespace.is_empty())
+        .unwrap_or("default");
+
+    // Kubernetes has no access to the certificate files, so they are embedded and exported as secrets
+    let mut config = state.config();
+    config.cert_path.clear();
+
+    let config = generate_traefik_config(&conn, &config).await.merged();
+
+    Download::new(
+        ContentType::new("application", "yaml"),
+        "traefik-gui-kubernetes.yaml",
+        render(&config, namespace),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_long_names_unique() {
+        assert_eq!(resource_name("My_Route.example"), "my-route-example");
+
+        let long = "a".repeat(70);
+        let other = format!("{}b", long);
+        assert_eq!(resource_name(&long).len(), 63);
+        assert!(resource_name(&long).starts_with(&"a".repeat(54)));
+        assert_ne!(resource_name(&long), resource_name(&other));
+    }
+}
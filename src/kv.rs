@@ -0,0 +1,230 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::{config::Config, output::GeneratedConfig, traefik::TraefikConfig};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Error)]
+pub enum KvError {
+    #[error("error serializing config: {0}")]
+    Serialize(#[from] serde_json::Error),
+    #[error("error publishing config to redis: {0}")]
+    Redis(#[from] redis::RedisError),
+}
+
+/// Flattens the config into the key layout of the traefik KV providers,
+/// e.g. `traefik/http/routers/<name>/rule`. Lists use the index as key.
+pub fn flatten(
+    root_key: &str,
+    generated: &GeneratedConfig,
+) -> Result<BTreeMap<String, String>, KvError> {
+    let mut pairs = BTreeMap::new();
+
+    for config in generated.configs() {
+        flatten_config(root_key, config, &mut pairs)?;
+    }
+
+    Ok(pairs)
+}
+
+fn flatten_config(
+    root_key: &str,
+    config: &TraefikConfig,
+    pairs: &mut BTreeMap<String, String>,
+) -> Result<(), KvError> {
+    let value = serde_json::to_value(config)?;
+    flatten_value(root_key.trim_end_matches('/').to_string(), value, pairs);
+    Ok(())
+}
+
+fn flatten_value(key: String, value: Value, pairs: &mut BTreeMap<String, String>) {
+    match value {
+        // an empty section like `tls: {}` only enables the feature, which traefik expects as `true`
+        Value::Object(map) if map.is_empty() => {
+            pairs.insert(key, "true".into());
+        }
+        Value::Object(map) => {
+            for (name, value) in map {
+                flatten_value(format!("{}/{}", key, name), value, pairs);
+            }
+        }
+        Value::Array(values) => {
+            for (index, value) in values.into_iter().enumerate() {
+                flatten_value(format!("{}/{}", key, index), value, pairs);
+            }
+        }
+        Value::String(value) => {
+            pairs.insert(key, value);
+        }
+        Value::Bool(value) => {
+            pairs.insert(key, value.to_string());
+        }
+        Value::Number(value) => {
+            pairs.insert(key, value.to_string());
+        }
+        Value::Null => {}
+    }
+}
+
+/// Replaces everything below the root key with the given pairs.
+/// Keys from previous exports that are no longer generated are deleted.
+pub fn publish(url: &str, root_key: &str, pairs: BTreeMap<String, String>) -> Result<(), KvError> {
+    let client = redis::Client::open(url)?;
+    let mut conn = client.get_connection_with_timeout(TIMEOUT)?;
+    conn.set_read_timeout(Some(TIMEOUT))?;
+    conn.set_write_timeout(Some(TIMEOUT))?;
+
+    let pattern = format!("{}/*", root_key.trim_end_matches('/'));
+    let existing: Vec<String> = redis::cmd("KEYS").arg(&pattern).query(&mut conn)?;
+
+    let mut pipe = redis::pipe();
+    pipe.atomic();
+
+    for key in existing.iter().filter(|key| !pairs.contains_key(*key)) {
+        pipe.cmd("DEL").arg(key).ignore();
+    }
+    for (key, value) in &pairs {
+        pipe.cmd("SET").arg(key).arg(value).ignore();
+    }
+
+    pipe.query::<()>(&mut conn)?;
+
+    Ok(())
+}
+
+/// Publishes the config to the KV store, if one is configured
+pub async fn export(generated: &GeneratedConfig, config: &Config) {
+    if config.kv_url.is_empty() {
+        return;
+    }
+
+    let pairs = match flatten(&config.kv_root_key, generated) {
+        Ok(pairs) => pairs,
+        Err(e) => {
+            error!("{}", e);
+            return;
+        }
+    };
+
+    let url = config.kv_url.clone();
+    let root_key = config.kv_root_key.clone();
+    let published =
+        rocket::tokio::task::spawn_blocking(move || publish(&url, &root_key, pairs)).await;
+
+    match published {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => error!("{}", e),
+        Err(e) => error!("error publishing config to redis: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traefik::{
+        HttpCompress, HttpLoadBalancer, HttpMiddleware, HttpRouter, HttpServer, HttpService,
+        HttpTls, TlsDomain,
+    };
+
+    #[test]
+    fn flattens_to_kv_layout() {
+        let mut common = TraefikConfig::new();
+        common.http.middlewares.insert(
+            "gui-default-compress".into(),
+            HttpMiddleware {
+                compress: Some(HttpCompress {}),
+                ..Default::default()
+            },
+        );
+
+        let mut route = TraefikConfig::new();
+        route.http.routers.insert(
+            "gui-https-1-app".into(),
+            HttpRouter {
+                rule: "Host(`app.example.com`)".into(),
+                service: "gui-https-1-app".into(),
+                priority: Some(10),
+                middlewares: vec!["gui-default-compress".into(), "gui-errors-1-default".into()],
+                tls: Some(HttpTls {
+                    cert_resolver: Some("le".into()),
+                    domains: vec![TlsDomain {
+                        main: "example.com".into(),
+                        sans: vec!["*.example.com".into()],
+                    }],
+                    options: None,
+                }),
+            },
+        );
+        route.http.services.insert(
+            "gui-https-1-app".into(),
+            HttpService {
+                load_balancer: Some(HttpLoadBalancer {
+                    servers: vec![HttpServer {
+                        url: "http://10.0.0.5:8080".into(),
+                    }],
+                    health_check: None,
+                }),
+                ..Default::default()
+            },
+        );
+
+        let mut generated = GeneratedConfig::new(common);
+        generated
+            .routes
+            .entry("https")
+            .or_default()
+            .insert("gui-https-1-app".into(), route);
+
+        let pairs = flatten("traefik/", &generated).unwrap();
+        let pairs = pairs
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            pairs,
+            [
+                (
+                    "traefik/http/middlewares/gui-default-compress/compress",
+                    "true"
+                ),
+                (
+                    "traefik/http/routers/gui-https-1-app/middlewares/0",
+                    "gui-default-compress"
+                ),
+                (
+                    "traefik/http/routers/gui-https-1-app/middlewares/1",
+                    "gui-errors-1-default"
+                ),
+                ("traefik/http/routers/gui-https-1-app/priority", "10"),
+                (
+                    "traefik/http/routers/gui-https-1-app/rule",
+                    "Host(`app.example.com`)"
+                ),
+                (
+                    "traefik/http/routers/gui-https-1-app/service",
+                    "gui-https-1-app"
+                ),
+                (
+                    "traefik/http/routers/gui-https-1-app/tls/certResolver",
+                    "le"
+                ),
+                (
+                    "traefik/http/routers/gui-https-1-app/tls/domains/0/main",
+                    "example.com"
+                ),
+                (
+                    "traefik/http/routers/gui-https-1-app/tls/domains/0/sans/0",
+                    "*.example.com"
+                ),
+                (
+                    "traefik/http/services/gui-https-1-app/loadBalancer/servers/0/url",
+                    "http://10.0.0.5:8080"
+                ),
+            ]
+        );
+    }
+}
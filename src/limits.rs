@@ -0,0 +1,113 @@
+//! Rate and concurrency limits of HTTP and HTTPS routes.
+//!
+//! The middlewares are named after the router of the route. Both limits group the requests
+//! by the same source, the client IP by default.
+
+use crate::traefik::{
+    HttpInFlightReq, HttpMiddleware, HttpRateLimit, HttpSourceCriterion, TraefikConfig,
+};
+
+/// What the requests can be grouped by, see [`RouteLimits::limit_source`]
+pub const SOURCES: [&str; 3] = ["ip", "host", "header"];
+
+/// The limit settings of a route
+pub struct RouteLimits<'a> {
+    /// Requests allowed per period on average, `None` disables the rate limit
+    pub rate_limit_average: Option<i32>,
+    /// Requests allowed at once above the average
+    pub rate_limit_burst: Option<i32>,
+    /// Period of the average in seconds, one second if not set
+    pub rate_limit_period: Option<i32>,
+    /// One of [`SOURCES`], `None` groups by the client IP
+    pub limit_source: Option<&'a str>,
+    /// Header grouping the requests for the `header` source
+    pub limit_header: Option<&'a str>,
+    /// Requests processed at the same time, `None` disables the limit
+    pub in_flight_requests: Option<i32>,
+}
+
+impl RouteLimits<'_> {
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, value) in [
+            ("rate limit", self.rate_limit_average),
+            ("burst", self.rate_limit_burst),
+            ("rate limit period", self.rate_limit_period),
+            ("in-flight requests", self.in_flight_requests),
+        ] {
+            if value.is_some_and(|value| value < 1) {
+                return Err(format!("the {} has to be at least 1", name));
+            }
+        }
+
+        match self.limit_source {
+            None | Some("ip") | Some("host") => Ok(()),
+            Some("header") if self.limit_header.is_some() => Ok(()),
+            Some("header") => Err("grouping by header needs a header name".into()),
+            Some(source) => Err(format!("unknown limit source {}", source)),
+        }
+    }
+
+    fn source_criterion(&self) -> Option<HttpSourceCriterion> {
+        match self.limit_source {
+            Some("host") => Some(HttpSourceCriterion {
+                request_header_name: None,
+                request_host: true,
+            }),
+            Some("header") => Some(HttpSourceCriterion {
+                request_header_name: self.limit_header.map(String::from),
+                request_host: false,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Adds the middlewares limiting the requests, returns their names for the router
+    pub fn add_to_config(
+        &self,
+        traefik_config: &mut TraefikConfig,
+        router_name: &str,
+    ) -> Vec<String> {
+        let mut middlewares = Vec::new();
+
+        if let Some(average) = self.rate_limit_average {
+            middlewares.push((
+                format!("{}-rate-limit", router_name),
+                HttpMiddleware {
+                    rate_limit: Some(HttpRateLimit {
+                        average,
+                        burst: self.rate_limit_burst,
+                        period: self
+                            .rate_limit_period
+                            .map(|seconds| format!("{}s", seconds)),
+                        source_criterion: self.source_criterion(),
+                    }),
+                    ..Default::default()
+                },
+            ));
+        }
+
+        if let Some(amount) = self.in_flight_requests {
+            middlewares.push((
+                format!("{}-in-flight", router_name),
+                HttpMiddleware {
+                    in_flight_req: Some(HttpInFlightReq {
+                        amount,
+                        source_criterion: self.source_criterion(),
+                    }),
+                    ..Default::default()
+                },
+            ));
+        }
+
+        middlewares
+            .into_iter()
+            .map(|(name, middleware)| {
+                traefik_config
+                    .http
+                    .middlewares
+                    .insert(name.clone(), middleware);
+                name
+            })
+            .collect()
+    }
+}
@@ -1,13 +1,17 @@
 use config::{Config, ConfigState};
+use diesel_migrations::{embed_migrations, EmbeddedMigrations};
+use output::GeneratedConfig;
 use rocket::{
     fairing::AdHoc,
     fs::FileServer,
+    http::{ContentType, Header},
     request::FlashMessage,
     response::{Flash, Redirect},
     serde::Serialize,
     Build, Rocket, State,
 };
 use rocket_dyn_templates::Template;
+use traefik::TraefikConfig;
 
 #[macro_use]
 extern crate rocket;
@@ -16,12 +20,33 @@ extern crate rocket_sync_db_pools;
 #[macro_use]
 extern crate diesel;
 
+mod acme;
+mod auth;
+mod backup;
+mod certificates;
+mod check;
 pub mod config;
+mod error_pages;
+mod failover;
+mod headers;
 mod http;
 mod https;
+mod kubernetes;
+mod kv;
+mod limits;
+mod maintenance;
+mod mirroring;
+mod output;
+mod provider;
+mod redirect;
+mod rewrite;
 mod schema;
+mod status;
 mod tls;
+mod tls_options;
 mod traefik;
+mod v1;
+mod x509;
 
 const ACME_PATH: &str = "/.well-known/acme-challenge/";
 
@@ -40,12 +65,14 @@ async fn rocket() -> _ {
                 http::create,
                 http::update,
                 http::enable,
+                http::set_maintenance,
                 http::delete,
                 http::to_https,
                 https::index,
                 https::create,
                 https::update,
                 https::enable,
+                https::set_maintenance,
                 https::delete,
                 https::to_http,
                 tls::index,
@@ -53,25 +80,73 @@ async fn rocket() -> _ {
                 tls::update,
                 tls::enable,
                 tls::delete,
+                redirect::index,
+                redirect::create,
+                redirect::update,
+                redirect::enable,
+                redirect::delete,
+                maintenance::page,
+                maintenance::page_post,
+                maintenance::page_put,
+                maintenance::page_delete,
+                maintenance::page_patch,
+                maintenance::page_options,
+                error_pages::index,
+                error_pages::create,
+                error_pages::update,
+                error_pages::delete,
+                error_pages::page,
+                headers::index,
+                headers::create,
+                headers::update,
+                headers::delete,
+                auth::index,
+                auth::create,
+                auth::update,
+                auth::delete,
+                auth::set_user,
+                auth::delete_user,
+                tls_options::index,
+                tls_options::create,
+                tls_options::update,
+                tls_options::delete,
+                acme::index,
+                certificates::index,
+                certificates::create,
+                certificates::set_default,
+                certificates::delete,
                 config::index,
-                config::update
+                config::update,
+                backup::export,
+                backup::restore,
+                v1::import,
+                provider::config,
+                kubernetes::export,
+                check::all,
+                check::http,
+                check::https,
+                check::tls
             ],
         )
         .mount("/static", FileServer::from("templates/static"))
         .attach(Template::fairing())
         .attach(DbConn::fairing())
         .attach(AdHoc::on_ignite("Run Migrations", run_migrations))
+        .attach(AdHoc::on_ignite("Import V1 Data", v1::import_on_startup))
         .attach(AdHoc::on_ignite(
             "Export Traefik Config",
             initialize_traefik_config,
         ))
+        .attach(AdHoc::on_liftoff("Backend Checks", |rocket| {
+            Box::pin(check::start_background_checks(rocket))
+        }))
         .manage(config::ConfigState::load().unwrap())
 }
 
-async fn run_migrations(rocket: Rocket<Build>) -> Rocket<Build> {
-    use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations");
 
-    const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations");
+async fn run_migrations(rocket: Rocket<Build>) -> Rocket<Build> {
+    use diesel_migrations::MigrationHarness;
 
     DbConn::get_one(&rocket)
         .await
@@ -85,13 +160,33 @@ async fn run_migrations(rocket: Rocket<Build>) -> Rocket<Build> {
     rocket
 }
 
+/// A response the browser offers to save as a file instead of displaying it
+#[derive(Responder)]
+pub struct Download {
+    inner: (ContentType, String),
+    disposition: Header<'static>,
+}
+
+impl Download {
+    pub fn new(content_type: ContentType, filename: &str, body: String) -> Self {
+        Self {
+            inner: (content_type, body),
+            disposition: Header::new(
+                "Content-Disposition",
+                format!("attachment; filename=\"{}\"", filename),
+            ),
+        }
+    }
+}
+
 #[derive(Serialize)]
 struct Index {
     flash: Option<(String, String)>,
     http_count: i64,
     https_count: i64,
     tls_count: i64,
-    config: String,
+    redirect_count: i64,
+    files: Vec<(String, String)>,
 }
 
 #[get("/")]
@@ -103,7 +198,19 @@ async fn index(
     let http_count = http::HttpRoute::count(&conn).await.unwrap_or(0);
     let https_count = https::HttpsRoute::count(&conn).await.unwrap_or(0);
     let tls_count = tls::TlsRoute::count(&conn).await.unwrap_or(0);
-    let config = generate_traefik_config(&conn, &config.config()).await;
+    let redirect_count = redirect::RedirectRoute::count(&conn).await.unwrap_or(0);
+    let config = config.config();
+    let files = generate_traefik_config(&conn, &config)
+        .await
+        .split(config.output_split)
+        .into_iter()
+        .map(|(name, file)| {
+            (
+                format!("{}.{}", name, config.output_format.extension()),
+                config.output_format.serialize(&file),
+            )
+        })
+        .collect();
     Template::render(
         "index",
         &Index {
@@ -111,7 +218,8 @@ async fn index(
             http_count,
             https_count,
             tls_count,
-            config,
+            redirect_count,
+            files,
         },
     )
 }
@@ -123,25 +231,60 @@ async fn redeploy(conn: DbConn, config: &State<ConfigState>) -> Flash<Redirect>
     Flash::success(Redirect::to("/"), "Traefik config updated")
 }
 
-async fn generate_traefik_config(conn: &DbConn, config: &Config) -> String {
-    let mut traefik_config = tls::TlsRoute::generate_traefik_config(conn).await;
-    let http = http::HttpRoute::generate_traefik_config(conn).await;
-    let https = https::HttpsRoute::generate_traefik_config(conn, config).await;
-
-    traefik_config.http.merge(http);
-    traefik_config.http.merge(https);
+pub async fn generate_traefik_config(conn: &DbConn, config: &Config) -> GeneratedConfig {
+    let mut common = TraefikConfig::new();
+    common.http.add_default_middlewares(config);
+    common.tls = certificates::Certificate::generate_traefik_config(conn, config).await;
+    common.tls.options = tls_options::TlsOption::generate_traefik_config(conn, config).await;
+    error_pages::ErrorPage::generate_traefik_config(&mut common, conn, config).await;
+    common
+        .http
+        .middlewares
+        .extend(headers::HeaderProfile::generate_traefik_config(conn).await);
+    common
+        .http
+        .middlewares
+        .extend(auth::AuthRealm::generate_traefik_config(conn).await);
 
-    traefik_config.http.add_default_middlewares();
+    let mut generated = GeneratedConfig::new(common);
+    generated.add_routes(
+        "http",
+        http::HttpRoute::generate_traefik_config(conn, config).await,
+    );
+    generated.add_routes(
+        "https",
+        https::HttpsRoute::generate_traefik_config(conn, config).await,
+    );
+    generated.add_routes(
+        "tls",
+        tls::TlsRoute::generate_traefik_config(conn, config).await,
+    );
+    generated.add_routes(
+        "redirect",
+        redirect::RedirectRoute::generate_traefik_config(conn, config).await,
+    );
 
-    let serialized = serde_yaml::to_string(&traefik_config).unwrap();
-
-    serialized
+    generated
 }
 
 pub async fn export_traefik_config(conn: &DbConn, config: &Config) {
-    let config = generate_traefik_config(conn, config).await;
+    let generated = generate_traefik_config(conn, config).await;
+
+    kv::export(&generated, config).await;
+
+    let files = generated.split(config.output_split);
+
+    if let Err(e) = output::write_files(&files, config.output_format) {
+        error!("Error writing traefik config: {}", e);
+    }
+
+    if let Err(e) = certificates::write_files(conn).await {
+        error!("Error writing certificates: {}", e);
+    }
 
-    std::fs::write("./traefik/gui.yml", config).unwrap();
+    if let Err(e) = tls_options::write_files(conn).await {
+        error!("Error writing client CAs: {}", e);
+    }
 }
 
 async fn initialize_traefik_config(rocket: Rocket<Build>) -> Rocket<Build> {
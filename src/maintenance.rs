@@ -0,0 +1,114 @@
+//! Maintenance mode of HTTP and HTTPS routes.
+//!
+//! A route in maintenance keeps its router, but traefik sends the requests to the GUI,
+//! which answers with a status page and `503 Service Unavailable`.
+
+use rocket::{http::Header, response::content::RawHtml, Either};
+use rocket_dyn_templates::{context, Template};
+
+use crate::{
+    config::Config,
+    http::HttpRoute,
+    https::HttpsRoute,
+    traefik::{
+        HttpLoadBalancer, HttpMiddleware, HttpReplacePath, HttpServer, HttpService, TraefikConfig,
+    },
+    DbConn,
+};
+
+/// Seconds clients are asked to wait before retrying
+const RETRY_AFTER: &str = "300";
+
+/// Adds the service and middleware sending the requests of a route to its maintenance page.
+/// Returns the service and middlewares for the router of the route.
+pub fn add_to_config(
+    traefik_config: &mut TraefikConfig,
+    router_name: &str,
+    kind: &str,
+    id: i32,
+    config: &Config,
+) -> (String, Vec<String>) {
+    let name = format!("{}-maintenance", router_name);
+
+    traefik_config.http.services.insert(
+        name.clone(),
+        HttpService {
+            load_balancer: Some(HttpLoadBalancer {
+                servers: vec![HttpServer {
+                    url: config.gui_url.trim_end_matches('/').to_string(),
+                }],
+                health_check: None,
+            }),
+            ..Default::default()
+        },
+    );
+
+    traefik_config.http.middlewares.insert(
+        name.clone(),
+        HttpMiddleware {
+            replace_path: Some(HttpReplacePath {
+                path: format!("/maintenance/{}/{}", kind, id),
+            }),
+            ..Default::default()
+        },
+    );
+
+    (name.clone(), vec![name])
+}
+
+#[derive(Responder)]
+#[response(status = 503)]
+pub struct MaintenancePage {
+    inner: Either<RawHtml<String>, Template>,
+    retry_after: Header<'static>,
+}
+
+// traefik forwards the requests with their method, so every method gets the page instead of a 404.
+// HEAD is answered by the GET route.
+#[get("/maintenance/<kind>/<id>")]
+pub async fn page(kind: &str, id: i32, conn: DbConn) -> Option<MaintenancePage> {
+    render(kind, id, &conn).await
+}
+
+#[post("/maintenance/<kind>/<id>")]
+pub async fn page_post(kind: &str, id: i32, conn: DbConn) -> Option<MaintenancePage> {
+    render(kind, id, &conn).await
+}
+
+#[put("/maintenance/<kind>/<id>")]
+pub async fn page_put(kind: &str, id: i32, conn: DbConn) -> Option<MaintenancePage> {
+    render(kind, id, &conn).await
+}
+
+#[delete("/maintenance/<kind>/<id>")]
+pub async fn page_delete(kind: &str, id: i32, conn: DbConn) -> Option<MaintenancePage> {
+    render(kind, id, &conn).await
+}
+
+#[patch("/maintenance/<kind>/<id>")]
+pub async fn page_patch(kind: &str, id: i32, conn: DbConn) -> Option<MaintenancePage> {
+    render(kind, id, &conn).await
+}
+
+#[options("/maintenance/<kind>/<id>")]
+pub async fn page_options(kind: &str, id: i32, conn: DbConn) -> Option<MaintenancePage> {
+    render(kind, id, &conn).await
+}
+
+async fn render(kind: &str, id: i32, conn: &DbConn) -> Option<MaintenancePage> {
+    let maintenance_html = match kind {
+        "http" => HttpRoute::get(id, conn).await.ok()?.maintenance_html,
+        "https" => HttpsRoute::get(id, conn).await.ok()?.maintenance_html,
+        _ => return None,
+    };
+
+    let inner = match maintenance_html {
+        Some(html) => Either::Left(RawHtml(html)),
+        None => Either::Right(Template::render("maintenance", context! {})),
+    };
+
+    Some(MaintenancePage {
+        inner,
+        retry_after: Header::new("Retry-After", RETRY_AFTER),
+    })
+}
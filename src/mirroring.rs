@@ -0,0 +1,87 @@
+//! Traffic mirroring of HTTPS routes, e.g. to test a new release with production traffic.
+//!
+//! The route keeps answering from its target, traefik copies a share of the requests
+//! to every mirror and drops their responses.
+
+use crate::traefik::{
+    HttpLoadBalancer, HttpMirror, HttpMirroring, HttpServer, HttpService, TraefikConfig,
+};
+
+/// A target receiving a copy of the requests
+pub struct Mirror {
+    pub target: String,
+    /// Share of the requests copied to the target
+    pub percent: u8,
+}
+
+/// Parses one `target percent` per line, e.g. `http://staging:8080 10`
+pub fn parse(mirrors: &str) -> Result<Vec<Mirror>, String> {
+    mirrors
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let (target, percent) = line
+                .rsplit_once(char::is_whitespace)
+                .ok_or_else(|| format!("invalid mirror {}, expected target and percent", line))?;
+            let percent = percent
+                .trim_end_matches('%')
+                .parse::<u8>()
+                .ok()
+                .filter(|percent| *percent <= 100)
+                .ok_or_else(|| format!("invalid percent of mirror {}", target.trim()))?;
+
+            Ok(Mirror {
+                target: target.trim().to_string(),
+                percent,
+            })
+        })
+        .collect()
+}
+
+/// Adds a service per mirror and the mirroring service in front of `service`.
+/// Returns the mirroring service for the router.
+pub fn add_to_config(
+    traefik_config: &mut TraefikConfig,
+    router_name: &str,
+    service: &str,
+    mirrors: Vec<Mirror>,
+) -> String {
+    let name = format!("{}-mirroring", router_name);
+
+    let mirrors = mirrors
+        .into_iter()
+        .enumerate()
+        .map(|(i, mirror)| {
+            let mirror_name = format!("{}-mirror-{}", router_name, i);
+            traefik_config.http.services.insert(
+                mirror_name.clone(),
+                HttpService {
+                    load_balancer: Some(HttpLoadBalancer {
+                        servers: vec![HttpServer { url: mirror.target }],
+                        health_check: None,
+                    }),
+                    ..Default::default()
+                },
+            );
+
+            HttpMirror {
+                name: mirror_name,
+                percent: mirror.percent,
+            }
+        })
+        .collect();
+
+    traefik_config.http.services.insert(
+        name.clone(),
+        HttpService {
+            mirroring: Some(HttpMirroring {
+                service: service.to_string(),
+                mirrors,
+            }),
+            ..Default::default()
+        },
+    );
+
+    name
+}
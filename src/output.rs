@@ -0,0 +1,154 @@
+use std::{collections::BTreeMap, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::traefik::TraefikConfig;
+
+const OUTPUT_DIR: &str = "./traefik";
+const OUTPUT_NAME: &str = "gui";
+
+#[derive(Serialize, Deserialize, FromFormField, Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    #[default]
+    Yaml,
+    Toml,
+}
+
+impl OutputFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Yaml => "yml",
+            OutputFormat::Toml => "toml",
+        }
+    }
+
+    pub fn serialize(self, config: &TraefikConfig) -> String {
+        match self {
+            OutputFormat::Yaml => serde_yaml::to_string(config).unwrap(),
+            OutputFormat::Toml => toml::to_string(config).unwrap(),
+        }
+    }
+}
+
+/// How the generated config is split across files in the providers directory.
+/// Splitting only organizes the files, traefik still loads the whole directory at once
+/// and keeps its previous config if any file fails to load.
+#[derive(Serialize, Deserialize, FromFormField, Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputSplit {
+    /// everything in `gui.yml`
+    #[default]
+    Single,
+    /// one file per route kind, e.g. `gui-https.yml`
+    Kind,
+    /// one file per route, named after its router, e.g. `gui-https-1-example.yml`
+    Route,
+}
+
+/// The generated config, grouped by route kind and router name,
+/// plus everything that is shared between the routes.
+pub struct GeneratedConfig {
+    pub common: TraefikConfig,
+    pub routes: BTreeMap<&'static str, BTreeMap<String, TraefikConfig>>,
+}
+
+impl GeneratedConfig {
+    pub fn new(common: TraefikConfig) -> Self {
+        Self {
+            common,
+            routes: BTreeMap::new(),
+        }
+    }
+
+    pub fn add_routes(&mut self, kind: &'static str, routes: BTreeMap<String, TraefikConfig>) {
+        self.routes.entry(kind).or_default().extend(routes);
+    }
+
+    pub fn configs(&self) -> impl Iterator<Item = &TraefikConfig> {
+        std::iter::once(&self.common).chain(self.routes.values().flat_map(BTreeMap::values))
+    }
+
+    pub fn merged(self) -> TraefikConfig {
+        let mut config = self.common;
+        for route in self.routes.into_values().flat_map(BTreeMap::into_values) {
+            config.merge(route);
+        }
+        config
+    }
+
+    /// Splits the config into files, keyed by the file name without extension
+    pub fn split(self, split: OutputSplit) -> BTreeMap<String, TraefikConfig> {
+        let mut files = BTreeMap::new();
+
+        match split {
+            OutputSplit::Single => {
+                files.insert(OUTPUT_NAME.to_string(), self.merged());
+            }
+            OutputSplit::Kind => {
+                files.insert(OUTPUT_NAME.to_string(), self.common);
+                for (kind, routes) in self.routes {
+                    let mut config = TraefikConfig::new();
+                    for route in routes.into_values() {
+                        config.merge(route);
+                    }
+                    if !config.is_empty() {
+                        files.insert(format!("{}-{}", OUTPUT_NAME, kind), config);
+                    }
+                }
+            }
+            OutputSplit::Route => {
+                files.insert(OUTPUT_NAME.to_string(), self.common);
+                // router names already start with `gui-`
+                for (name, route) in self.routes.into_values().flatten() {
+                    files.insert(file_name(&name), route);
+                }
+            }
+        }
+
+        files
+    }
+}
+
+/// Router names contain user input, so everything that might not be valid in a file name is replaced
+pub fn file_name(router_name: &str) -> String {
+    router_name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Writes the files to the output directory and removes all files from previous exports that are no longer generated
+pub fn write_files(
+    files: &BTreeMap<String, TraefikConfig>,
+    format: OutputFormat,
+) -> std::io::Result<()> {
+    let dir = Path::new(OUTPUT_DIR);
+
+    let mut written = Vec::new();
+    for (name, config) in files {
+        let file_name = format!("{}.{}", name, format.extension());
+        std::fs::write(dir.join(&file_name), format.serialize(config))?;
+        written.push(file_name);
+    }
+
+    for entry in std::fs::read_dir(dir)? {
+        let file_name = entry?.file_name().to_string_lossy().to_string();
+
+        let generated = (file_name.starts_with(&format!("{}.", OUTPUT_NAME))
+            || file_name.starts_with(&format!("{}-", OUTPUT_NAME)))
+            && (file_name.ends_with(".yml") || file_name.ends_with(".toml"));
+
+        if generated && !written.contains(&file_name) {
+            std::fs::remove_file(dir.join(&file_name))?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,123 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use rocket::{
+    http::Header,
+    request::{FromRequest, Outcome},
+    Request, State,
+};
+use subtle::ConstantTimeEq;
+
+use crate::{config::ConfigState, generate_traefik_config, DbConn};
+
+/// Headers of a poll from the traefik HTTP provider
+pub struct ProviderRequest<'r> {
+    authorization: Option<&'r str>,
+    if_none_match: Option<&'r str>,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ProviderRequest<'r> {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(Self {
+            authorization: request.headers().get_one("Authorization"),
+            if_none_match: request.headers().get_one("If-None-Match"),
+        })
+    }
+}
+
+#[derive(Responder)]
+pub enum ProviderResponse {
+    #[response(status = 200, content_type = "json")]
+    Config(String, Header<'static>),
+    #[response(status = 304)]
+    NotModified((), Header<'static>),
+    #[response(status = 401)]
+    Unauthorized(&'static str, Header<'static>),
+}
+
+fn etag(body: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    body.hash(&mut hasher);
+    format!("\"{:016x}\"", hasher.finish())
+}
+
+/// Whether `If-None-Match` matches `etag`, see RFC 9110 section 13.1.2.
+/// The header is `*` or a list of entity tags, which are compared weakly.
+fn none_match(if_none_match: &str, etag: &str) -> bool {
+    if if_none_match.trim() == "*" {
+        return true;
+    }
+
+    if_none_match
+        .split(',')
+        .map(|tag| tag.trim())
+        .any(|tag| tag.strip_prefix("W/").unwrap_or(tag) == etag)
+}
+
+/// Compares the bearer token in constant time, so the token can't be guessed by timing
+fn authorized(authorization: Option<&str>, token: &str) -> bool {
+    let expected = format!("Bearer {}", token);
+    authorization.is_some_and(|authorization| {
+        bool::from(authorization.as_bytes().ct_eq(expected.as_bytes()))
+    })
+}
+
+/// The generated config in the format of the traefik HTTP provider.
+/// Traefik polls this endpoint, so the GUI doesn't have to share a volume with traefik.
+#[get("/provider")]
+pub async fn config(
+    request: ProviderRequest<'_>,
+    conn: DbConn,
+    state: &State<ConfigState>,
+) -> ProviderResponse {
+    let config = state.config();
+
+    if !config.provider_token.is_empty()
+        && !authorized(request.authorization, &config.provider_token)
+    {
+        return ProviderResponse::Unauthorized(
+            "invalid or missing bearer token",
+            Header::new("WWW-Authenticate", "Bearer"),
+        );
+    }
+
+    let traefik_config = generate_traefik_config(&conn, &config).await.merged();
+    let body = serde_json::to_string(&traefik_config).unwrap();
+    let etag = etag(&body);
+
+    if request
+        .if_none_match
+        .is_some_and(|if_none_match| none_match(if_none_match, &etag))
+    {
+        ProviderResponse::NotModified((), Header::new("ETag", etag))
+    } else {
+        ProviderResponse::Config(body, Header::new("ETag", etag))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_if_none_match() {
+        let etag = "\"0123456789abcdef\"";
+
+        assert!(none_match(etag, etag));
+        assert!(none_match("*", etag));
+        assert!(none_match("W/\"0123456789abcdef\"", etag));
+        assert!(none_match("\"other\", W/\"0123456789abcdef\"", etag));
+        assert!(!none_match("\"other\"", etag));
+        assert!(!none_match("0123456789abcdef", etag));
+    }
+
+    #[test]
+    fn checks_bearer_token() {
+        assert!(authorized(Some("Bearer secret"), "secret"));
+        assert!(!authorized(Some("Bearer secreT"), "secret"));
+        assert!(!authorized(Some("Bearer secret2"), "secret"));
+        assert!(!authorized(None, "secret"));
+    }
+}
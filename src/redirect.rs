@@ -0,0 +1,382 @@
+//! Redirect routes, e.g. from `www.example.com` to `example.com` or from an old domain to a new one.
+//!
+//! They have no backend, the router points to `noop@internal` and a `redirectRegex` middleware answers.
+
+use std::collections::BTreeMap;
+
+use diesel::{ExpressionMethods, QueryDsl, QueryResult, RunQueryDsl};
+use itertools::Itertools;
+use rocket::{
+    form::Form,
+    request::FlashMessage,
+    response::{Flash, Redirect},
+    State,
+};
+use rocket_dyn_templates::Template;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::{Config, ConfigState},
+    export_traefik_config,
+    schema::redirect_routes::{self, dsl},
+    status::{self, RouteView},
+    traefik::{HttpMiddleware, HttpRedirectRegex, HttpRouter, HttpTls, TraefikConfig},
+    DbConn,
+};
+
+#[derive(Serialize, Deserialize, Queryable, Insertable, AsChangeset, FromForm, Clone, Debug)]
+#[serde(crate = "rocket::serde")]
+#[diesel(table_name = redirect_routes, treat_none_as_null = true)]
+pub struct RedirectRoute {
+    pub id: Option<i32>,
+    pub enabled: bool,
+    pub name: String,
+    pub priority: Option<i32>,
+    /// Comma separated source hosts
+    pub host: String,
+    /// URL to redirect to, e.g. `https://example.com`
+    pub destination: String,
+    /// Append the path and query of the request to the destination
+    pub preserve_path: bool,
+    /// 301/308 instead of 302/307
+    pub permanent: bool,
+    pub on_http: bool,
+    pub on_https: bool,
+}
+
+/// Escapes a host for a Go regular expression
+fn escape_regex(host: &str) -> String {
+    host.chars()
+        .map(|c| {
+            if "\\.+*?()|[]{}^$".contains(c) {
+                format!("\\{}", c)
+            } else {
+                c.to_string()
+            }
+        })
+        .collect()
+}
+
+impl RedirectRoute {
+    pub async fn count(conn: &DbConn) -> QueryResult<i64> {
+        conn.run(|c| redirect_routes::table.count().first::<i64>(c))
+            .await
+    }
+
+    pub async fn all(conn: &DbConn) -> QueryResult<Vec<RedirectRoute>> {
+        conn.run(|c| redirect_routes::table.load::<RedirectRoute>(c))
+            .await
+    }
+
+    pub async fn insert(route: RedirectRoute, conn: &DbConn) -> QueryResult<usize> {
+        conn.run(move |c| {
+            diesel::insert_into(redirect_routes::table)
+                .values(&route)
+                .execute(c)
+        })
+        .await
+    }
+
+    pub async fn update(id: i32, route: RedirectRoute, conn: &DbConn) -> QueryResult<usize> {
+        conn.run(move |c| {
+            diesel::update(redirect_routes::table)
+                .filter(dsl::id.eq(id))
+                .set(&route)
+                .execute(c)
+        })
+        .await
+    }
+
+    pub async fn delete(id: i32, conn: &DbConn) -> QueryResult<usize> {
+        conn.run(move |c| {
+            diesel::delete(redirect_routes::table)
+                .filter(dsl::id.eq(id))
+                .execute(c)
+        })
+        .await
+    }
+
+    pub async fn enable(id: i32, enabled: bool, conn: &DbConn) -> QueryResult<usize> {
+        conn.run(move |c| {
+            diesel::update(redirect_routes::table)
+                .filter(dsl::id.eq(id))
+                .set(dsl::enabled.eq(enabled))
+                .execute(c)
+        })
+        .await
+    }
+
+    pub fn cleanup(&mut self) {
+        self.name = self.name.trim().to_string();
+        self.host = self.host.trim().to_string();
+        self.destination = self.destination.trim().to_string();
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.hosts().next().is_none() {
+            return Err("A redirect needs at least one source host".into());
+        }
+        if !self.on_http && !self.on_https {
+            return Err("A redirect has to be active on HTTP, HTTPS or both".into());
+        }
+
+        let destination = reqwest::Url::parse(self.destination.trim())
+            .map_err(|e| format!("The destination has to be an absolute URL: {}", e))?;
+        if !matches!(destination.scheme(), "http" | "https") || !destination.has_host() {
+            return Err("The destination has to be an http:// or https:// URL".into());
+        }
+
+        Ok(())
+    }
+
+    pub fn router_name(&self) -> String {
+        format!("gui-redirect-{}-{}", self.id.unwrap_or_default(), self.name)
+    }
+
+    /// The router the status is shown for, the HTTP router is a helper if both are generated
+    pub fn status_router_name(&self) -> String {
+        if self.on_https {
+            self.router_name()
+        } else {
+            format!("{}-http", self.router_name())
+        }
+    }
+
+    fn hosts(&self) -> impl Iterator<Item = &str> {
+        self.host
+            .split(',')
+            .map(str::trim)
+            .filter(|host| !host.is_empty())
+    }
+
+    pub fn middleware(&self) -> HttpMiddleware {
+        let hosts = self.hosts().map(escape_regex).join("|");
+        // `$` starts a group reference in the replacement, `$$` is a literal one
+        let destination = self.destination.trim().replace('$', "$$");
+
+        let (regex, replacement) = if self.preserve_path {
+            (
+                format!("^https?://(?:{})(?::[0-9]+)?(.*)$", hosts),
+                format!("{}${{1}}", destination.trim_end_matches('/')),
+            )
+        } else {
+            (
+                format!("^https?://(?:{})(?::[0-9]+)?(?:/.*)?$", hosts),
+                destination,
+            )
+        };
+
+        HttpMiddleware {
+            redirect_regex: Some(HttpRedirectRegex {
+                regex,
+                replacement,
+                permanent: self.permanent,
+            }),
+            ..Default::default()
+        }
+    }
+
+    pub async fn generate_traefik_config(
+        conn: &DbConn,
+        config: &Config,
+    ) -> BTreeMap<String, TraefikConfig> {
+        let mut configs = BTreeMap::new();
+
+        let routes = RedirectRoute::all(conn).await.unwrap();
+
+        let acme_provider = if config.acme_provider_name.is_empty() {
+            None
+        } else {
+            Some(config.acme_provider_name.clone())
+        };
+
+        for route in routes {
+            if route.enabled {
+                let mut traefik_config = TraefikConfig::new();
+                let router_name = route.router_name();
+
+                let rule = route
+                    .hosts()
+                    .map(|host| format!("Host(`{}`)", host))
+                    .join(" || ");
+
+                traefik_config
+                    .http
+                    .middlewares
+                    .insert(router_name.clone(), route.middleware());
+
+                if route.on_http {
+                    traefik_config.http.routers.insert(
+                        format!("{}-http", router_name),
+                        HttpRouter {
+                            rule: rule.clone(),
+                            service: "noop@internal".into(),
+                            priority: route.priority,
+                            middlewares: vec![router_name.clone()],
+                            tls: None,
+                        },
+                    );
+                }
+
+                if route.on_https {
+                    traefik_config.http.routers.insert(
+                        router_name.clone(),
+                        HttpRouter {
+                            rule,
+                            service: "noop@internal".into(),
+                            priority: route.priority,
+                            middlewares: vec![router_name.clone()],
+                            tls: Some(HttpTls {
+                                cert_resolver: acme_provider.clone(),
+                                domains: Vec::new(),
+                                options: None,
+                            }),
+                        },
+                    );
+                }
+
+                configs.insert(router_name, traefik_config);
+            }
+        }
+
+        configs
+    }
+}
+
+#[derive(Serialize)]
+struct Redirects {
+    flash: Option<(String, String)>,
+    routes: Vec<RouteView<RedirectRoute>>,
+    edit: Option<i32>,
+    status_error: Option<String>,
+}
+
+impl Redirects {
+    pub async fn raw(
+        conn: &DbConn,
+        config: &Config,
+        flash: Option<(String, String)>,
+        edit: Option<i32>,
+    ) -> Self {
+        let (status, status_error) = match status::load(config).await {
+            Ok(status) => (status, None),
+            Err(e) => (None, Some(e)),
+        };
+
+        match RedirectRoute::all(conn).await {
+            Ok(routes) => Self {
+                flash,
+                routes: routes
+                    .into_iter()
+                    .map(|route| {
+                        let router_name = route.status_router_name();
+                        RouteView::new(route, &router_name, status.as_ref(), None)
+                    })
+                    .collect(),
+                edit,
+                status_error,
+            },
+            Err(e) => {
+                error!("DB error loading redirect routes: {}", e);
+                Self {
+                    flash: Some(("error".into(), e.to_string())),
+                    routes: Vec::new(),
+                    edit: None,
+                    status_error,
+                }
+            }
+        }
+    }
+}
+
+#[get("/redirect?<edit>")]
+pub async fn index(
+    edit: Option<i32>,
+    flash: Option<FlashMessage<'_>>,
+    conn: DbConn,
+    config: &State<ConfigState>,
+) -> Template {
+    let flash = flash.map(FlashMessage::into_inner);
+    Template::render(
+        "redirect",
+        Redirects::raw(&conn, &config.config(), flash, edit).await,
+    )
+}
+
+#[post("/redirect", data = "<route_form>")]
+pub async fn create(
+    route_form: Form<RedirectRoute>,
+    conn: DbConn,
+    config: &State<ConfigState>,
+) -> Flash<Redirect> {
+    let mut route = route_form.into_inner();
+    route.cleanup();
+
+    if let Err(e) = route.validate() {
+        return Flash::error(Redirect::to("/redirect"), e);
+    }
+
+    if let Err(e) = RedirectRoute::insert(route, &conn).await {
+        Flash::error(Redirect::to("/redirect"), e.to_string())
+    } else {
+        export_traefik_config(&conn, &config.config()).await;
+        Flash::success(Redirect::to("/redirect"), "Redirect created")
+    }
+}
+
+#[post("/redirect/<id>", data = "<route_form>")]
+pub async fn update(
+    id: i32,
+    route_form: Form<RedirectRoute>,
+    conn: DbConn,
+    config: &State<ConfigState>,
+) -> Flash<Redirect> {
+    let mut route = route_form.into_inner();
+    route.cleanup();
+
+    if let Err(e) = route.validate() {
+        return Flash::error(Redirect::to(format!("/redirect?edit={}", id)), e);
+    }
+
+    if let Err(e) = RedirectRoute::update(id, route, &conn).await {
+        Flash::error(Redirect::to("/redirect"), e.to_string())
+    } else {
+        export_traefik_config(&conn, &config.config()).await;
+        Flash::success(Redirect::to("/redirect"), "Redirect updated")
+    }
+}
+
+#[post("/redirect/<id>/enable", data = "<enabled>")]
+pub async fn enable(
+    id: i32,
+    enabled: Form<bool>,
+    conn: DbConn,
+    config: &State<ConfigState>,
+) -> Flash<Redirect> {
+    let enabled = enabled.into_inner();
+    if let Err(e) = RedirectRoute::enable(id, enabled, &conn).await {
+        Flash::error(Redirect::to("/redirect"), e.to_string())
+    } else {
+        export_traefik_config(&conn, &config.config()).await;
+        Flash::success(Redirect::to("/redirect"), "Redirect updated")
+    }
+}
+
+#[post("/redirect/<id>/delete", data = "<confirm>")]
+pub async fn delete(
+    id: i32,
+    confirm: Form<bool>,
+    conn: DbConn,
+    config: &State<ConfigState>,
+) -> Flash<Redirect> {
+    if confirm.into_inner() {
+        if let Err(e) = RedirectRoute::delete(id, &conn).await {
+            Flash::error(Redirect::to("/redirect"), e.to_string())
+        } else {
+            export_traefik_config(&conn, &config.config()).await;
+            Flash::success(Redirect::to("/redirect"), "Redirect deleted")
+        }
+    } else {
+        Flash::error(Redirect::to("/redirect"), "Delete cancelled")
+    }
+}
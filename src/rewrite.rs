@@ -0,0 +1,101 @@
+//! Path rewriting of HTTP and HTTPS routes, so the target doesn't have to know the route prefix.
+//!
+//! The middlewares are named after the router of the route and run in the order
+//! strip prefix, replace by regex, add prefix.
+
+use crate::traefik::{
+    HttpAddPrefix, HttpMiddleware, HttpReplacePathRegex, HttpStripPrefix, TraefikConfig,
+};
+
+/// The rewrite settings of a route
+pub struct PathRewrite<'a> {
+    /// Prefix matched by the route, removed with `strip_prefix`
+    pub prefix: Option<&'a str>,
+    pub strip_prefix: bool,
+    pub add_prefix: Option<&'a str>,
+    pub path_regex: Option<&'a str>,
+    pub path_replacement: Option<&'a str>,
+}
+
+impl PathRewrite<'_> {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.strip_prefix && self.prefix.is_none() {
+            return Err("stripping the prefix needs a path prefix".into());
+        }
+
+        if self
+            .add_prefix
+            .is_some_and(|prefix| !prefix.starts_with('/'))
+        {
+            return Err("the added prefix has to start with /".into());
+        }
+
+        if self.path_regex.is_some() != self.path_replacement.is_some() {
+            return Err("a path regex needs a replacement and vice versa".into());
+        }
+
+        // traefik uses Go's RE2 syntax, which the regex crate follows closely
+        if let Some(path_regex) = self.path_regex {
+            regex::Regex::new(path_regex).map_err(|e| format!("invalid path regex: {}", e))?;
+        }
+
+        Ok(())
+    }
+
+    /// Adds the middlewares rewriting the path, returns their names for the router
+    pub fn add_to_config(
+        &self,
+        traefik_config: &mut TraefikConfig,
+        router_name: &str,
+    ) -> Vec<String> {
+        let mut middlewares = Vec::new();
+
+        if let (true, Some(prefix)) = (self.strip_prefix, self.prefix) {
+            middlewares.push((
+                format!("{}-strip-prefix", router_name),
+                HttpMiddleware {
+                    strip_prefix: Some(HttpStripPrefix {
+                        prefixes: vec![prefix.to_string()],
+                    }),
+                    ..Default::default()
+                },
+            ));
+        }
+
+        if let (Some(regex), Some(replacement)) = (self.path_regex, self.path_replacement) {
+            middlewares.push((
+                format!("{}-replace-path-regex", router_name),
+                HttpMiddleware {
+                    replace_path_regex: Some(HttpReplacePathRegex {
+                        regex: regex.to_string(),
+                        replacement: replacement.to_string(),
+                    }),
+                    ..Default::default()
+                },
+            ));
+        }
+
+        if let Some(prefix) = self.add_prefix {
+            middlewares.push((
+                format!("{}-add-prefix", router_name),
+                HttpMiddleware {
+                    add_prefix: Some(HttpAddPrefix {
+                        prefix: prefix.to_string(),
+                    }),
+                    ..Default::default()
+                },
+            ));
+        }
+
+        middlewares
+            .into_iter()
+            .map(|(name, middleware)| {
+                traefik_config
+                    .http
+                    .middlewares
+                    .insert(name.clone(), middleware);
+                name
+            })
+            .collect()
+    }
+}
@@ -1,5 +1,56 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    auth_realms (id) {
+        id -> Nullable<Integer>,
+        name -> Text,
+        digest -> Bool,
+        remove_header -> Bool,
+        users -> Text,
+    }
+}
+
+diesel::table! {
+    certificates (id) {
+        id -> Nullable<Integer>,
+        name -> Text,
+        cert_pem -> Text,
+        key_pem -> Text,
+        default_certificate -> Bool,
+    }
+}
+
+diesel::table! {
+    error_pages (id) {
+        id -> Nullable<Integer>,
+        name -> Text,
+        status -> Text,
+        html -> Text,
+        global -> Bool,
+    }
+}
+
+diesel::table! {
+    header_profiles (id) {
+        id -> Nullable<Integer>,
+        name -> Text,
+        sts_seconds -> Nullable<Integer>,
+        sts_include_subdomains -> Bool,
+        sts_preload -> Bool,
+        frame_deny -> Bool,
+        content_type_nosniff -> Bool,
+        browser_xss_filter -> Bool,
+        referrer_policy -> Nullable<Text>,
+        content_security_policy -> Nullable<Text>,
+        custom_request_headers -> Nullable<Text>,
+        custom_response_headers -> Nullable<Text>,
+        cors_allow_origins -> Nullable<Text>,
+        cors_allow_methods -> Nullable<Text>,
+        cors_allow_headers -> Nullable<Text>,
+        cors_allow_credentials -> Bool,
+    }
+}
+
 diesel::table! {
     http_routes (id) {
         id -> Nullable<Integer>,
@@ -10,6 +61,25 @@ diesel::table! {
         host_regex -> Bool,
         host -> Text,
         prefix -> Nullable<Text>,
+        maintenance -> Bool,
+        maintenance_html -> Nullable<Text>,
+        error_page -> Nullable<Text>,
+        skip_default_middlewares -> Bool,
+        strip_prefix -> Bool,
+        add_prefix -> Nullable<Text>,
+        path_regex -> Nullable<Text>,
+        path_replacement -> Nullable<Text>,
+        auth_realm -> Nullable<Text>,
+        forward_auth -> Bool,
+        rate_limit_average -> Nullable<Integer>,
+        rate_limit_burst -> Nullable<Integer>,
+        rate_limit_period -> Nullable<Integer>,
+        limit_source -> Nullable<Text>,
+        limit_header -> Nullable<Text>,
+        in_flight_requests -> Nullable<Integer>,
+        fallback_target -> Nullable<Text>,
+        health_check_path -> Nullable<Text>,
+        health_check_interval -> Nullable<Integer>,
     }
 }
 
@@ -24,6 +94,12 @@ diesel::table! {
         host -> Text,
         acme_http_passthrough -> Nullable<Integer>,
         https_redirect -> Bool,
+        terminate_tls -> Bool,
+        cert_resolver -> Nullable<Text>,
+        tls_options -> Nullable<Text>,
+        proxy_protocol -> Nullable<Integer>,
+        in_flight_connections -> Nullable<Integer>,
+        ip_allow_list -> Nullable<Text>,
     }
 }
 
@@ -39,6 +115,69 @@ diesel::table! {
         prefix -> Nullable<Text>,
         https_redirect -> Bool,
         allow_http_acme -> Bool,
+        cert_resolver -> Nullable<Text>,
+        tls_domains -> Nullable<Text>,
+        tls_options -> Nullable<Text>,
+        maintenance -> Bool,
+        maintenance_html -> Nullable<Text>,
+        error_page -> Nullable<Text>,
+        skip_default_middlewares -> Bool,
+        header_profile -> Nullable<Text>,
+        strip_prefix -> Bool,
+        add_prefix -> Nullable<Text>,
+        path_regex -> Nullable<Text>,
+        path_replacement -> Nullable<Text>,
+        auth_realm -> Nullable<Text>,
+        forward_auth -> Bool,
+        rate_limit_average -> Nullable<Integer>,
+        rate_limit_burst -> Nullable<Integer>,
+        rate_limit_period -> Nullable<Integer>,
+        limit_source -> Nullable<Text>,
+        limit_header -> Nullable<Text>,
+        in_flight_requests -> Nullable<Integer>,
+        mirrors -> Nullable<Text>,
+        fallback_target -> Nullable<Text>,
+        health_check_path -> Nullable<Text>,
+        health_check_interval -> Nullable<Integer>,
+    }
+}
+
+diesel::table! {
+    redirect_routes (id) {
+        id -> Nullable<Integer>,
+        enabled -> Bool,
+        name -> Text,
+        priority -> Nullable<Integer>,
+        host -> Text,
+        destination -> Text,
+        preserve_path -> Bool,
+        permanent -> Bool,
+        on_http -> Bool,
+        on_https -> Bool,
+    }
+}
+
+diesel::table! {
+    route_checks (id) {
+        id -> Nullable<Integer>,
+        kind -> Text,
+        route_id -> Integer,
+        checked_at -> BigInt,
+        success -> Bool,
+        latency_ms -> Nullable<Integer>,
+        error -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    tls_options (id) {
+        id -> Nullable<Integer>,
+        name -> Text,
+        min_version -> Nullable<Text>,
+        cipher_suites -> Nullable<Text>,
+        sni_strict -> Bool,
+        client_auth_type -> Nullable<Text>,
+        client_ca -> Nullable<Text>,
     }
 }
 
@@ -0,0 +1,189 @@
+//! Live status of the generated routers, as reported by the traefik API.
+
+use std::{collections::HashMap, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{check::RouteCheck, config::Config};
+
+const TIMEOUT: Duration = Duration::from_secs(3);
+const PER_PAGE: u32 = 100;
+
+#[derive(Deserialize)]
+struct ApiRouter {
+    name: String,
+    status: String,
+    #[serde(default)]
+    error: Vec<String>,
+    #[serde(default)]
+    service: String,
+}
+
+#[derive(Deserialize)]
+struct ApiService {
+    name: String,
+    #[serde(default, rename = "serverStatus")]
+    server_status: HashMap<String, String>,
+    #[serde(default)]
+    error: Vec<String>,
+}
+
+/// Status of a route and the helper routers generated for it
+#[derive(Serialize, Debug)]
+pub struct RouteStatus {
+    /// `enabled`, `warning` or `disabled` as reported by traefik
+    pub status: String,
+    pub errors: Vec<String>,
+    /// server URL and its health check state, e.g. `UP`
+    pub servers: Vec<(String, String)>,
+}
+
+pub struct TraefikStatus {
+    routers: HashMap<String, ApiRouter>,
+    services: HashMap<String, ApiService>,
+}
+
+/// Names in the API are suffixed with the provider, e.g. `gui-http-1-example@file`
+fn strip_provider(name: &str) -> &str {
+    name.split('@').next().unwrap_or(name)
+}
+
+/// Loads all pages of a list from the traefik API.
+/// Traefik returns 100 items per page and the following page in `X-Next-Page`, `1` after the last page.
+async fn fetch<T: for<'de> Deserialize<'de>>(
+    client: &reqwest::Client,
+    url: &str,
+) -> reqwest::Result<Vec<T>> {
+    let mut items = Vec::new();
+    let mut page = 1;
+
+    loop {
+        let response = client
+            .get(url)
+            .query(&[("page", page), ("per_page", PER_PAGE)])
+            .send()
+            .await?
+            .error_for_status()?;
+
+        let next_page = response
+            .headers()
+            .get("X-Next-Page")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u32>().ok());
+
+        items.extend(response.json::<Vec<T>>().await?);
+
+        match next_page {
+            Some(next_page) if next_page > page => page = next_page,
+            _ => return Ok(items),
+        }
+    }
+}
+
+impl TraefikStatus {
+    pub async fn fetch(api_url: &str) -> Result<Self, String> {
+        let client = reqwest::Client::builder()
+            .timeout(TIMEOUT)
+            .build()
+            .map_err(|e| e.to_string())?;
+        let api_url = api_url.trim_end_matches('/');
+
+        let http_routers_url = format!("{}/api/http/routers", api_url);
+        let tcp_routers_url = format!("{}/api/tcp/routers", api_url);
+        let services_url = format!("{}/api/http/services", api_url);
+
+        let (http_routers, tcp_routers, services) = rocket::tokio::join!(
+            fetch::<ApiRouter>(&client, &http_routers_url),
+            fetch::<ApiRouter>(&client, &tcp_routers_url),
+            fetch::<ApiService>(&client, &services_url),
+        );
+
+        let error = |e: reqwest::Error| format!("error loading status from traefik API: {}", e);
+
+        let routers = http_routers
+            .map_err(error)?
+            .into_iter()
+            .chain(tcp_routers.map_err(error)?)
+            .map(|router| (strip_provider(&router.name).to_string(), router))
+            .collect();
+
+        let services = services
+            .map_err(error)?
+            .into_iter()
+            .map(|service| (strip_provider(&service.name).to_string(), service))
+            .collect();
+
+        Ok(Self { routers, services })
+    }
+
+    /// Looks up the status of a route by the name of its main router
+    pub fn route(&self, router_name: &str) -> Option<RouteStatus> {
+        let router = self.routers.get(router_name)?;
+
+        let mut status = RouteStatus {
+            status: router.status.clone(),
+            errors: router.error.clone(),
+            servers: Vec::new(),
+        };
+
+        for suffix in ["redirect", "acme", "http"] {
+            if let Some(helper) = self.routers.get(&format!("{}-{}", router_name, suffix)) {
+                if helper.status != "enabled" && status.status == "enabled" {
+                    status.status = "warning".into();
+                }
+                status.errors.extend(helper.error.iter().cloned());
+            }
+        }
+
+        if let Some(service) = self.services.get(strip_provider(&router.service)) {
+            status.errors.extend(service.error.iter().cloned());
+            status.servers = service
+                .server_status
+                .iter()
+                .map(|(url, state)| (url.clone(), state.clone()))
+                .collect();
+            status.servers.sort();
+
+            if status.status == "enabled" && status.servers.iter().any(|(_, state)| state != "UP") {
+                status.status = "warning".into();
+            }
+        }
+
+        Some(status)
+    }
+}
+
+/// A route with its live status and the last check of its target, for rendering the route tables
+#[derive(Serialize)]
+pub struct RouteView<T: Serialize> {
+    #[serde(flatten)]
+    pub route: T,
+    pub status: Option<RouteStatus>,
+    pub check: Option<RouteCheck>,
+}
+
+impl<T: Serialize> RouteView<T> {
+    pub fn new(
+        route: T,
+        router_name: &str,
+        status: Option<&TraefikStatus>,
+        check: Option<RouteCheck>,
+    ) -> Self {
+        Self {
+            route,
+            status: status.and_then(|status| status.route(router_name)),
+            check,
+        }
+    }
+}
+
+/// Loads the status from the traefik API, if an API URL is configured
+pub async fn load(config: &Config) -> Result<Option<TraefikStatus>, String> {
+    if config.traefik_api_url.is_empty() {
+        return Ok(None);
+    }
+
+    TraefikStatus::fetch(&config.traefik_api_url)
+        .await
+        .map(Some)
+}
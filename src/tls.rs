@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 use diesel::{ExpressionMethods, QueryDsl, QueryResult, RunQueryDsl};
 use rocket::{
     form::Form,
@@ -6,22 +8,27 @@ use rocket::{
     State,
 };
 use rocket_dyn_templates::Template;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    config::ConfigState,
+    check::RouteCheck,
+    config::{Config, ConfigState, ProxyProtocolMode},
     export_traefik_config,
+    https::NO_CERT_RESOLVER,
     schema::tls_routes,
+    status::{self, RouteView},
+    tls_options::TlsOption,
     traefik::{
-        HttpLoadBalancer, HttpRouter, HttpServer, HttpService, TcpLoadBalancer, TcpRouter,
-        TcpServer, TcpService, TcpTls, TraefikConfig,
+        HttpLoadBalancer, HttpRouter, HttpServer, HttpService, ProxyProtocol, TcpInFlightConn,
+        TcpIpAllowList, TcpLoadBalancer, TcpMiddleware, TcpRouter, TcpServer, TcpServersTransport,
+        TcpService, TcpTls, TraefikConfig,
     },
     DbConn, ACME_PATH,
 };
 
-#[derive(Serialize, Queryable, Insertable, AsChangeset, FromForm, Clone, Debug)]
+#[derive(Serialize, Deserialize, Queryable, Insertable, AsChangeset, FromForm, Clone, Debug)]
 #[serde(crate = "rocket::serde")]
-#[diesel(table_name = tls_routes)]
+#[diesel(table_name = tls_routes, treat_none_as_null = true)]
 pub struct TlsRoute {
     pub id: Option<i32>,
     pub enabled: bool,
@@ -32,6 +39,21 @@ pub struct TlsRoute {
     pub host: String,
     pub acme_http_passthrough: Option<i32>,
     pub https_redirect: bool,
+    /// Terminate TLS at traefik and forward plain TCP, instead of passing TLS through to the target
+    #[serde(default)]
+    pub terminate_tls: bool,
+    /// Only used when terminating TLS, see [`crate::https::HttpsRoute::cert_resolver`]
+    pub cert_resolver: Option<String>,
+    /// Only used when terminating TLS
+    pub tls_options: Option<String>,
+    /// PROXY protocol version sent to the target, so it sees the real client IP
+    pub proxy_protocol: Option<i32>,
+    /// Connections per client IP at the same time, `None` disables the limit
+    #[serde(default)]
+    pub in_flight_connections: Option<i32>,
+    /// Comma separated IPs and CIDR ranges allowed to connect, `None` allows everyone
+    #[serde(default)]
+    pub ip_allow_list: Option<String>,
 }
 
 impl TlsRoute {
@@ -44,7 +66,13 @@ impl TlsRoute {
         conn.run(|c| tls_routes::table.load::<TlsRoute>(c)).await
     }
 
-    pub async fn insert(route: TlsRoute, conn: &DbConn) -> QueryResult<usize> {
+    pub async fn get(id: i32, conn: &DbConn) -> QueryResult<TlsRoute> {
+        conn.run(move |c| tls_routes::table.filter(tls_routes::id.eq(id)).first(c))
+            .await
+    }
+
+    pub async fn insert(mut route: TlsRoute, conn: &DbConn) -> QueryResult<usize> {
+        route.cleanup();
         conn.run(move |c| {
             diesel::insert_into(tls_routes::table)
                 .values(&route)
@@ -53,7 +81,8 @@ impl TlsRoute {
         .await
     }
 
-    pub async fn update(id: i32, route: TlsRoute, conn: &DbConn) -> QueryResult<usize> {
+    pub async fn update(id: i32, mut route: TlsRoute, conn: &DbConn) -> QueryResult<usize> {
+        route.cleanup();
         conn.run(move |c| {
             diesel::update(tls_routes::table)
                 .filter(tls_routes::id.eq(id))
@@ -64,6 +93,7 @@ impl TlsRoute {
     }
 
     pub async fn delete(id: i32, conn: &DbConn) -> QueryResult<usize> {
+        RouteCheck::delete("tls", id, conn).await?;
         conn.run(move |c| {
             diesel::delete(tls_routes::table)
                 .filter(tls_routes::id.eq(id))
@@ -82,14 +112,141 @@ impl TlsRoute {
         .await
     }
 
-    pub async fn generate_traefik_config(conn: &DbConn) -> TraefikConfig {
+    /// The target with the default port 443 if none is set
+    pub fn target_address(&self) -> String {
+        let mut target = self.target.clone();
+        if target.rfind(':').is_none() {
+            target.push_str(":443");
+        }
+        target
+    }
+
+    pub fn router_name(&self) -> String {
+        format!("gui-tls-{}-{}", self.id.unwrap_or_default(), self.name)
+    }
+
+    pub fn cleanup(&mut self) {
+        if let Some(cert_resolver) = &self.cert_resolver {
+            if cert_resolver.trim().is_empty() {
+                self.cert_resolver = None;
+            }
+        }
+        if let Some(tls_options) = &self.tls_options {
+            if tls_options.trim().is_empty() {
+                self.tls_options = None;
+            }
+        }
+        if let Some(ip_allow_list) = &self.ip_allow_list {
+            let source_range = ip_allow_list
+                .split(',')
+                .map(str::trim)
+                .filter(|range| !range.is_empty())
+                .collect::<Vec<_>>();
+            self.ip_allow_list = if source_range.is_empty() {
+                None
+            } else {
+                Some(source_range.join(", "))
+            };
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.in_flight_connections.is_some_and(|amount| amount < 1) {
+            return Err("the in-flight connections have to be at least 1".into());
+        }
+        if self
+            .proxy_protocol
+            .is_some_and(|version| !matches!(version, 1 | 2))
+        {
+            return Err("the PROXY protocol version has to be 1 or 2".into());
+        }
+        Ok(())
+    }
+
+    /// Adds the middlewares of the route, returns their names for the router
+    fn add_middlewares(
+        &self,
+        traefik_config: &mut TraefikConfig,
+        router_name: &str,
+    ) -> Vec<String> {
+        let mut middlewares = Vec::new();
+
+        // the allow list comes first, so blocked connections aren't counted
+        if let Some(ip_allow_list) = &self.ip_allow_list {
+            middlewares.push((
+                format!("{}-ip-allow-list", router_name),
+                TcpMiddleware {
+                    ip_allow_list: Some(TcpIpAllowList {
+                        source_range: ip_allow_list.split(", ").map(String::from).collect(),
+                    }),
+                    ..Default::default()
+                },
+            ));
+        }
+
+        if let Some(amount) = self.in_flight_connections {
+            middlewares.push((
+                format!("{}-in-flight-conn", router_name),
+                TcpMiddleware {
+                    in_flight_conn: Some(TcpInFlightConn { amount }),
+                    ..Default::default()
+                },
+            ));
+        }
+
+        middlewares
+            .into_iter()
+            .map(|(name, middleware)| {
+                traefik_config
+                    .tcp
+                    .middlewares
+                    .insert(name.clone(), middleware);
+                name
+            })
+            .collect()
+    }
+
+    pub fn tls(&self, default_resolver: &Option<String>) -> TcpTls {
+        if !self.terminate_tls {
+            return TcpTls {
+                passthrough: true,
+                cert_resolver: None,
+                options: None,
+            };
+        }
+
+        let cert_resolver = match self.cert_resolver.as_deref().map(str::trim) {
+            None => default_resolver.clone(),
+            Some(NO_CERT_RESOLVER) => None,
+            Some(resolver) => Some(resolver.to_string()),
+        };
+
+        TcpTls {
+            passthrough: false,
+            cert_resolver,
+            options: self.tls_options.clone(),
+        }
+    }
+
+    pub async fn generate_traefik_config(
+        conn: &DbConn,
+        app_config: &Config,
+    ) -> BTreeMap<String, TraefikConfig> {
         let routes = TlsRoute::all(conn).await.unwrap();
 
-        let mut config = TraefikConfig::new();
+        let acme_provider = if app_config.acme_provider_name.is_empty() {
+            None
+        } else {
+            Some(app_config.acme_provider_name.clone())
+        };
 
-        for route in routes {
+        let mut configs = BTreeMap::new();
+
+        for mut route in routes {
             if route.enabled {
-                let router_name = format!("gui-tls-{}-{}", route.id.unwrap(), route.name);
+                route.cleanup();
+                let mut config = TraefikConfig::new();
+                let router_name = route.router_name();
                 let host_rule = if route.host_regex {
                     format!("HostSNIRegexp(`{}`)", route.host)
                 } else {
@@ -102,31 +259,51 @@ impl TlsRoute {
                     format!("Host(`{}`)", route.host)
                 };
 
+                let middlewares = route.add_middlewares(&mut config, &router_name);
+
                 config.tcp.routers.insert(
                     router_name.clone(),
                     TcpRouter {
                         priority: route.priority,
                         service: router_name.clone(),
                         rule: host_rule,
-                        tls: Some(TcpTls { passthrough: true }),
+                        middlewares,
+                        tls: Some(route.tls(&acme_provider)),
                     },
                 );
 
-                let mut target = route.target.clone();
-                if target.rfind(':') == None {
-                    target.push_str(":443");
+                let target = route.target_address();
+
+                let proxy_protocol = route
+                    .proxy_protocol
+                    .map(|version| ProxyProtocol { version });
+
+                let mut load_balancer = TcpLoadBalancer {
+                    servers: vec![TcpServer { address: target }],
+                    proxy_protocol: None,
+                    servers_transport: None,
+                };
+
+                match (proxy_protocol, app_config.proxy_protocol_mode) {
+                    (None, _) => {}
+                    (Some(proxy_protocol), ProxyProtocolMode::LoadBalancer) => {
+                        load_balancer.proxy_protocol = Some(proxy_protocol);
+                    }
+                    (Some(proxy_protocol), ProxyProtocolMode::ServersTransport) => {
+                        config.tcp.servers_transports.insert(
+                            router_name.clone(),
+                            TcpServersTransport {
+                                proxy_protocol: Some(proxy_protocol),
+                            },
+                        );
+                        load_balancer.servers_transport = Some(router_name.clone());
+                    }
                 }
 
-                config.tcp.services.insert(
-                    router_name.clone(),
-                    TcpService {
-                        load_balancer: TcpLoadBalancer {
-                            servers: vec![TcpServer {
-                                address: format!("{}", target),
-                            }],
-                        },
-                    },
-                );
+                config
+                    .tcp
+                    .services
+                    .insert(router_name.clone(), TcpService { load_balancer });
 
                 if let Some(acme_port) = route.acme_http_passthrough {
                     // find the last colon in the target and replace the port after it with the acme port
@@ -137,8 +314,7 @@ impl TlsRoute {
                         acme_target.push_str(&format!(":{}", acme_port));
                     }
 
-                    let acme_router_name =
-                        format!("gui-tls-{}-{}-acme", route.id.unwrap(), route.name);
+                    let acme_router_name = format!("{}-acme", router_name);
 
                     let acme_rule = format!("({} && PathPrefix(`{}`))", http_host_rule, ACME_PATH);
 
@@ -157,11 +333,13 @@ impl TlsRoute {
                     config.http.services.insert(
                         acme_router_name.clone(),
                         HttpService {
-                            load_balancer: HttpLoadBalancer {
+                            load_balancer: Some(HttpLoadBalancer {
                                 servers: vec![HttpServer {
                                     url: format!("http://{}", acme_target),
                                 }],
-                            },
+                                health_check: None,
+                            }),
+                            ..Default::default()
                         },
                     );
                 }
@@ -180,27 +358,56 @@ impl TlsRoute {
                         },
                     );
                 }
+
+                configs.insert(router_name, config);
             }
         }
 
-        config
+        configs
     }
 }
 
 #[derive(Serialize)]
 struct Tls {
     flash: Option<(String, String)>,
-    routes: Vec<TlsRoute>,
+    routes: Vec<RouteView<TlsRoute>>,
     edit: Option<i32>,
+    status_error: Option<String>,
+    resolvers: Vec<String>,
+    tls_options: Vec<String>,
 }
 
 impl Tls {
-    pub async fn raw(conn: &DbConn, flash: Option<(String, String)>, edit: Option<i32>) -> Self {
+    pub async fn raw(
+        conn: &DbConn,
+        config: &Config,
+        flash: Option<(String, String)>,
+        edit: Option<i32>,
+    ) -> Self {
+        let (status, status_error) = match status::load(config).await {
+            Ok(status) => (status, None),
+            Err(e) => (None, Some(e)),
+        };
+
+        let mut checks = RouteCheck::for_kind("tls", conn).await.unwrap_or_default();
+
+        let tls_options = TlsOption::names(conn).await.unwrap_or_default();
+
         match TlsRoute::all(conn).await {
             Ok(routes) => Self {
                 flash,
-                routes,
+                routes: routes
+                    .into_iter()
+                    .map(|route| {
+                        let router_name = route.router_name();
+                        let check = route.id.and_then(|id| checks.remove(&id));
+                        RouteView::new(route, &router_name, status.as_ref(), check)
+                    })
+                    .collect(),
                 edit,
+                status_error,
+                resolvers: config.resolvers(),
+                tls_options,
             },
             Err(e) => {
                 error!("DB error loading HTTP routes: {}", e);
@@ -208,6 +415,9 @@ impl Tls {
                     flash: Some(("error".into(), e.to_string())),
                     routes: Vec::new(),
                     edit: None,
+                    status_error,
+                    resolvers: config.resolvers(),
+                    tls_options,
                 }
             }
         }
@@ -215,9 +425,14 @@ impl Tls {
 }
 
 #[get("/tls?<edit>")]
-pub async fn index(edit: Option<i32>, flash: Option<FlashMessage<'_>>, conn: DbConn) -> Template {
+pub async fn index(
+    edit: Option<i32>,
+    flash: Option<FlashMessage<'_>>,
+    conn: DbConn,
+    config: &State<ConfigState>,
+) -> Template {
     let flash = flash.map(FlashMessage::into_inner);
-    Template::render("tls", Tls::raw(&conn, flash, edit).await)
+    Template::render("tls", Tls::raw(&conn, &config.config(), flash, edit).await)
 }
 
 #[post("/tls", data = "<route_form>")]
@@ -226,7 +441,12 @@ pub async fn create(
     conn: DbConn,
     config: &State<ConfigState>,
 ) -> Flash<Redirect> {
-    let route = route_form.into_inner();
+    let mut route = route_form.into_inner();
+    route.cleanup();
+    if let Err(e) = route.validate() {
+        return Flash::error(Redirect::to("/tls"), e);
+    }
+
     if let Err(e) = TlsRoute::insert(route, &conn).await {
         error!("DB error creating TLS route: {}", e);
         Flash::error(Redirect::to("/tls"), e.to_string())
@@ -246,7 +466,12 @@ pub async fn update(
     conn: DbConn,
     config: &State<ConfigState>,
 ) -> Flash<Redirect> {
-    let route = route_form.into_inner();
+    let mut route = route_form.into_inner();
+    route.cleanup();
+    if let Err(e) = route.validate() {
+        return Flash::error(Redirect::to(format!("/tls?edit={}", id)), e);
+    }
+
     if let Err(e) = TlsRoute::update(id, route, &conn).await {
         error!("DB error updating TLS route: {}", e);
         Flash::error(Redirect::to("/tls"), e.to_string())
@@ -0,0 +1,353 @@
+//! Named TLS options profiles, e.g. the minimum TLS version or client certificate authentication.
+//!
+//! A profile named `default` applies to all routes that don't select another one.
+
+use std::collections::BTreeMap;
+
+use diesel::{Connection, ExpressionMethods, QueryDsl, QueryResult, RunQueryDsl};
+use rocket::{
+    form::Form,
+    request::FlashMessage,
+    response::{Flash, Redirect},
+    State,
+};
+use rocket_dyn_templates::Template;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    certificates::{traefik_file, write_cert_files, CertFile},
+    config::{Config, ConfigState},
+    export_traefik_config, output,
+    schema::{https_routes, tls_options, tls_routes},
+    traefik::{TlsClientAuth, TlsOptions},
+    x509::CertificateInfo,
+    DbConn,
+};
+
+pub const TLS_VERSIONS: [&str; 4] = [
+    "VersionTLS10",
+    "VersionTLS11",
+    "VersionTLS12",
+    "VersionTLS13",
+];
+
+pub const CLIENT_AUTH_TYPES: [&str; 5] = [
+    "NoClientCert",
+    "RequestClientCert",
+    "RequireAnyClientCert",
+    "VerifyClientCertIfGiven",
+    "RequireAndVerifyClientCert",
+];
+
+#[derive(Debug, Error)]
+pub enum TlsOptionError {
+    #[error("the TLS options are used by {0}, select other options for these routes first")]
+    InUse(String),
+    #[error(transparent)]
+    Db(#[from] diesel::result::Error),
+}
+
+#[derive(Serialize, Deserialize, Queryable, Insertable, AsChangeset, FromForm, Clone, Debug)]
+#[serde(crate = "rocket::serde")]
+#[diesel(table_name = tls_options, treat_none_as_null = true)]
+pub struct TlsOption {
+    pub id: Option<i32>,
+    pub name: String,
+    pub min_version: Option<String>,
+    /// Comma separated cipher suites, only used up to TLS 1.2
+    pub cipher_suites: Option<String>,
+    pub sni_strict: bool,
+    pub client_auth_type: Option<String>,
+    /// PEM of the CAs client certificates are verified against
+    pub client_ca: Option<String>,
+}
+
+impl TlsOption {
+    pub async fn all(conn: &DbConn) -> QueryResult<Vec<TlsOption>> {
+        conn.run(|c| tls_options::table.load::<TlsOption>(c)).await
+    }
+
+    pub async fn names(conn: &DbConn) -> QueryResult<Vec<String>> {
+        conn.run(|c| {
+            tls_options::table
+                .select(tls_options::name)
+                .order(tls_options::name)
+                .load::<String>(c)
+        })
+        .await
+    }
+
+    pub async fn insert(mut option: TlsOption, conn: &DbConn) -> QueryResult<usize> {
+        option.cleanup();
+        conn.run(move |c| {
+            diesel::insert_into(tls_options::table)
+                .values(&option)
+                .execute(c)
+        })
+        .await
+    }
+
+    /// Updates the profile and the routes using it, in case it was renamed
+    pub async fn update(id: i32, mut option: TlsOption, conn: &DbConn) -> QueryResult<usize> {
+        option.cleanup();
+        conn.run(move |c| {
+            c.transaction(|c| {
+                let name = tls_options::table
+                    .filter(tls_options::id.eq(id))
+                    .select(tls_options::name)
+                    .first::<String>(c)?;
+
+                diesel::update(https_routes::table)
+                    .filter(https_routes::tls_options.eq(&name))
+                    .set(https_routes::tls_options.eq(&option.name))
+                    .execute(c)?;
+                diesel::update(tls_routes::table)
+                    .filter(tls_routes::tls_options.eq(&name))
+                    .set(tls_routes::tls_options.eq(&option.name))
+                    .execute(c)?;
+
+                diesel::update(tls_options::table)
+                    .filter(tls_options::id.eq(id))
+                    .set(&option)
+                    .execute(c)
+            })
+        })
+        .await
+    }
+
+    /// Deletes the profile. Profiles used by routes can't be deleted,
+    /// the routes would silently lose e.g. the client certificate authentication.
+    pub async fn delete(id: i32, conn: &DbConn) -> Result<usize, TlsOptionError> {
+        conn.run(move |c| {
+            c.transaction(|c| {
+                let name = tls_options::table
+                    .filter(tls_options::id.eq(id))
+                    .select(tls_options::name)
+                    .first::<String>(c)?;
+
+                let https = https_routes::table
+                    .filter(https_routes::tls_options.eq(&name))
+                    .select(https_routes::name)
+                    .load::<String>(c)?;
+                let tls = tls_routes::table
+                    .filter(tls_routes::tls_options.eq(&name))
+                    .select(tls_routes::name)
+                    .load::<String>(c)?;
+
+                let routes = https
+                    .iter()
+                    .map(|route| format!("HTTPS route {}", route))
+                    .chain(tls.iter().map(|route| format!("TLS route {}", route)))
+                    .collect::<Vec<_>>();
+                if !routes.is_empty() {
+                    return Err(TlsOptionError::InUse(routes.join(", ")));
+                }
+
+                Ok(diesel::delete(tls_options::table)
+                    .filter(tls_options::id.eq(id))
+                    .execute(c)?)
+            })
+        })
+        .await
+    }
+
+    pub fn cleanup(&mut self) {
+        self.name = self.name.trim().to_string();
+        for field in [
+            &mut self.min_version,
+            &mut self.cipher_suites,
+            &mut self.client_auth_type,
+            &mut self.client_ca,
+        ] {
+            if field.as_ref().is_some_and(|value| value.trim().is_empty()) {
+                *field = None;
+            }
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("the name can't be empty".into());
+        }
+
+        if let Some(client_ca) = &self.client_ca {
+            CertificateInfo::from_pem(client_ca.as_bytes())
+                .map_err(|e| format!("invalid client CA: {}", e))?;
+        }
+
+        let verifies = matches!(
+            self.client_auth_type.as_deref(),
+            Some("VerifyClientCertIfGiven" | "RequireAndVerifyClientCert")
+        );
+        if verifies && self.client_ca.is_none() {
+            return Err("verifying client certificates requires a client CA".into());
+        }
+
+        Ok(())
+    }
+
+    fn ca_file_name(&self) -> String {
+        output::file_name(&format!(
+            "gui-ca-{}-{}",
+            self.id.unwrap_or_default(),
+            self.name
+        )) + ".crt"
+    }
+
+    pub async fn generate_traefik_config(
+        conn: &DbConn,
+        config: &Config,
+    ) -> BTreeMap<String, TlsOptions> {
+        let options = TlsOption::all(conn).await.unwrap();
+
+        options
+            .into_iter()
+            .map(|option| {
+                let client_auth =
+                    option
+                        .client_auth_type
+                        .clone()
+                        .map(|client_auth_type| TlsClientAuth {
+                            ca_files: option
+                                .client_ca
+                                .iter()
+                                .map(|ca| traefik_file(config, &option.ca_file_name(), ca))
+                                .collect(),
+                            client_auth_type,
+                        });
+
+                let tls_options = TlsOptions {
+                    min_version: option.min_version,
+                    cipher_suites: option
+                        .cipher_suites
+                        .iter()
+                        .flat_map(|suites| suites.split(','))
+                        .map(str::trim)
+                        .filter(|suite| !suite.is_empty())
+                        .map(String::from)
+                        .collect(),
+                    sni_strict: option.sni_strict,
+                    client_auth,
+                };
+
+                (option.name, tls_options)
+            })
+            .collect()
+    }
+}
+
+/// Writes the client CA files and removes the files of deleted profiles
+pub async fn write_files(conn: &DbConn) -> std::io::Result<()> {
+    let options = TlsOption::all(conn).await.map_err(std::io::Error::other)?;
+
+    let files = options
+        .iter()
+        .filter_map(|option| {
+            option.client_ca.as_ref().map(|ca| CertFile {
+                name: option.ca_file_name(),
+                content: ca.clone(),
+                private: false,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    write_cert_files("gui-ca-", &files)
+}
+
+#[derive(Serialize)]
+struct TlsOptionsPage {
+    flash: Option<(String, String)>,
+    options: Vec<TlsOption>,
+    edit: Option<i32>,
+    tls_versions: [&'static str; 4],
+    client_auth_types: [&'static str; 5],
+}
+
+impl TlsOptionsPage {
+    pub async fn raw(conn: &DbConn, flash: Option<(String, String)>, edit: Option<i32>) -> Self {
+        let (options, flash) = match TlsOption::all(conn).await {
+            Ok(options) => (options, flash),
+            Err(e) => {
+                error!("DB error loading TLS options: {}", e);
+                (Vec::new(), Some(("error".into(), e.to_string())))
+            }
+        };
+
+        Self {
+            flash,
+            options,
+            edit,
+            tls_versions: TLS_VERSIONS,
+            client_auth_types: CLIENT_AUTH_TYPES,
+        }
+    }
+}
+
+#[get("/tls_options?<edit>")]
+pub async fn index(edit: Option<i32>, flash: Option<FlashMessage<'_>>, conn: DbConn) -> Template {
+    let flash = flash.map(FlashMessage::into_inner);
+    Template::render("tls_options", TlsOptionsPage::raw(&conn, flash, edit).await)
+}
+
+#[post("/tls_options", data = "<option_form>")]
+pub async fn create(
+    option_form: Form<TlsOption>,
+    conn: DbConn,
+    config: &State<ConfigState>,
+) -> Flash<Redirect> {
+    let mut option = option_form.into_inner();
+    option.cleanup();
+
+    if let Err(e) = option.validate() {
+        return Flash::error(Redirect::to("/tls_options"), e);
+    }
+
+    if let Err(e) = TlsOption::insert(option, &conn).await {
+        Flash::error(Redirect::to("/tls_options"), e.to_string())
+    } else {
+        export_traefik_config(&conn, &config.config()).await;
+        Flash::success(Redirect::to("/tls_options"), "TLS options created")
+    }
+}
+
+#[post("/tls_options/<id>", data = "<option_form>")]
+pub async fn update(
+    id: i32,
+    option_form: Form<TlsOption>,
+    conn: DbConn,
+    config: &State<ConfigState>,
+) -> Flash<Redirect> {
+    let mut option = option_form.into_inner();
+    option.cleanup();
+
+    if let Err(e) = option.validate() {
+        return Flash::error(Redirect::to(format!("/tls_options?edit={}", id)), e);
+    }
+
+    if let Err(e) = TlsOption::update(id, option, &conn).await {
+        Flash::error(Redirect::to("/tls_options"), e.to_string())
+    } else {
+        export_traefik_config(&conn, &config.config()).await;
+        Flash::success(Redirect::to("/tls_options"), "TLS options updated")
+    }
+}
+
+#[post("/tls_options/<id>/delete", data = "<confirm>")]
+pub async fn delete(
+    id: i32,
+    confirm: Form<bool>,
+    conn: DbConn,
+    config: &State<ConfigState>,
+) -> Flash<Redirect> {
+    if confirm.into_inner() {
+        if let Err(e) = TlsOption::delete(id, &conn).await {
+            Flash::error(Redirect::to("/tls_options"), e.to_string())
+        } else {
+            export_traefik_config(&conn, &config.config()).await;
+            Flash::success(Redirect::to("/tls_options"), "TLS options deleted")
+        }
+    } else {
+        Flash::error(Redirect::to("/tls_options"), "Delete cancelled")
+    }
+}
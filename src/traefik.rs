@@ -2,12 +2,16 @@ use std::collections::BTreeMap;
 
 use serde::Serialize;
 
+use crate::config::Config;
+
 #[derive(Serialize)]
 pub struct TraefikConfig {
     #[serde(skip_serializing_if = "HttpConfig::is_empty")]
     pub http: HttpConfig,
     #[serde(skip_serializing_if = "TcpConfig::is_empty")]
     pub tcp: TcpConfig,
+    #[serde(skip_serializing_if = "TlsConfig::is_empty")]
+    pub tls: TlsConfig,
 }
 
 impl TraefikConfig {
@@ -15,8 +19,19 @@ impl TraefikConfig {
         Self {
             http: HttpConfig::new(),
             tcp: TcpConfig::new(),
+            tls: TlsConfig::new(),
         }
     }
+
+    pub fn merge(&mut self, other: TraefikConfig) {
+        self.http.merge(other.http);
+        self.tcp.merge(other.tcp);
+        self.tls.merge(other.tls);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.http.is_empty() && self.tcp.is_empty() && self.tls.is_empty()
+    }
 }
 
 #[derive(Serialize)]
@@ -44,15 +59,19 @@ impl HttpConfig {
         self.middlewares.extend(other.middlewares);
     }
 
-    pub fn add_default_middlewares(&mut self) {
+    /// Adds the `https-redirect` middleware and the default middlewares from the config
+    pub fn add_default_middlewares(&mut self, config: &Config) {
         self.middlewares.insert(
             "https-redirect".into(),
             HttpMiddleware {
                 redirect_scheme: Some(HttpRedirectScheme {
                     scheme: HttpScheme::Https,
                 }),
+                ..Default::default()
             },
         );
+        self.middlewares.extend(config.default_middlewares());
+        self.middlewares.extend(config.forward_auth());
     }
 
     pub fn is_empty(&self) -> bool {
@@ -77,17 +96,64 @@ pub struct HttpTls {
     #[serde(rename = "certResolver")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cert_resolver: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub domains: Vec<TlsDomain>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub options: Option<String>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone)]
+pub struct TlsDomain {
+    pub main: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub sans: Vec<String>,
+}
+
+/// Exactly one of the fields is set, traefik names the service type by the key
+#[derive(Serialize, Default)]
 pub struct HttpService {
     #[serde(rename = "loadBalancer")]
-    pub load_balancer: HttpLoadBalancer,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub load_balancer: Option<HttpLoadBalancer>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mirroring: Option<HttpMirroring>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failover: Option<HttpFailover>,
+}
+
+/// Sends the requests to `service` and copies a share of them to the mirrors, ignoring their responses
+#[derive(Serialize)]
+pub struct HttpMirroring {
+    pub service: String,
+    pub mirrors: Vec<HttpMirror>,
+}
+
+#[derive(Serialize)]
+pub struct HttpMirror {
+    pub name: String,
+    pub percent: u8,
+}
+
+/// Sends the requests to `fallback` while the health check of `service` fails
+#[derive(Serialize)]
+pub struct HttpFailover {
+    pub service: String,
+    pub fallback: String,
 }
 
 #[derive(Serialize)]
 pub struct HttpLoadBalancer {
     pub servers: Vec<HttpServer>,
+    #[serde(rename = "healthCheck")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub health_check: Option<HttpHealthCheck>,
+}
+
+#[derive(Serialize)]
+pub struct HttpHealthCheck {
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interval: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -95,10 +161,202 @@ pub struct HttpServer {
     pub url: String,
 }
 
-#[derive(Serialize)]
+/// Exactly one of the fields is set, traefik names the middleware type by the key
+#[derive(Serialize, Default)]
 pub struct HttpMiddleware {
     #[serde(rename = "redirectScheme")]
-    redirect_scheme: Option<HttpRedirectScheme>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub redirect_scheme: Option<HttpRedirectScheme>,
+    #[serde(rename = "redirectRegex")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub redirect_regex: Option<HttpRedirectRegex>,
+    #[serde(rename = "replacePath")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replace_path: Option<HttpReplacePath>,
+    #[serde(rename = "replacePathRegex")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replace_path_regex: Option<HttpReplacePathRegex>,
+    #[serde(rename = "stripPrefix")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strip_prefix: Option<HttpStripPrefix>,
+    #[serde(rename = "addPrefix")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub add_prefix: Option<HttpAddPrefix>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub errors: Option<HttpErrors>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compress: Option<HttpCompress>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub headers: Option<HttpHeaders>,
+    #[serde(rename = "ipAllowList")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ip_allow_list: Option<HttpIpAllowList>,
+    #[serde(rename = "basicAuth")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub basic_auth: Option<HttpAuth>,
+    #[serde(rename = "digestAuth")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub digest_auth: Option<HttpAuth>,
+    #[serde(rename = "forwardAuth")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub forward_auth: Option<HttpForwardAuth>,
+    #[serde(rename = "rateLimit")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<HttpRateLimit>,
+    #[serde(rename = "inFlightReq")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub in_flight_req: Option<HttpInFlightReq>,
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+#[derive(Serialize)]
+pub struct HttpCompress {}
+
+#[derive(Serialize, Default)]
+pub struct HttpHeaders {
+    #[serde(rename = "frameDeny")]
+    #[serde(skip_serializing_if = "is_false")]
+    pub frame_deny: bool,
+    #[serde(rename = "contentTypeNosniff")]
+    #[serde(skip_serializing_if = "is_false")]
+    pub content_type_nosniff: bool,
+    #[serde(rename = "browserXssFilter")]
+    #[serde(skip_serializing_if = "is_false")]
+    pub browser_xss_filter: bool,
+    #[serde(rename = "referrerPolicy")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub referrer_policy: Option<String>,
+    /// Only sent on HTTPS requests
+    #[serde(rename = "stsSeconds")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sts_seconds: Option<u64>,
+    #[serde(rename = "stsIncludeSubdomains")]
+    #[serde(skip_serializing_if = "is_false")]
+    pub sts_include_subdomains: bool,
+    #[serde(rename = "stsPreload")]
+    #[serde(skip_serializing_if = "is_false")]
+    pub sts_preload: bool,
+    #[serde(rename = "contentSecurityPolicy")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_security_policy: Option<String>,
+    /// An empty value removes the header
+    #[serde(rename = "customRequestHeaders")]
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub custom_request_headers: BTreeMap<String, String>,
+    #[serde(rename = "customResponseHeaders")]
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub custom_response_headers: BTreeMap<String, String>,
+    #[serde(rename = "accessControlAllowOriginList")]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub access_control_allow_origin_list: Vec<String>,
+    #[serde(rename = "accessControlAllowMethods")]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub access_control_allow_methods: Vec<String>,
+    #[serde(rename = "accessControlAllowHeaders")]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub access_control_allow_headers: Vec<String>,
+    #[serde(rename = "accessControlAllowCredentials")]
+    #[serde(skip_serializing_if = "is_false")]
+    pub access_control_allow_credentials: bool,
+    #[serde(rename = "addVaryHeader")]
+    #[serde(skip_serializing_if = "is_false")]
+    pub add_vary_header: bool,
+}
+
+#[derive(Serialize)]
+pub struct HttpIpAllowList {
+    #[serde(rename = "sourceRange")]
+    pub source_range: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct HttpErrors {
+    pub status: Vec<String>,
+    pub service: String,
+    pub query: String,
+}
+
+/// Settings of the `basicAuth` and `digestAuth` middlewares
+#[derive(Serialize)]
+pub struct HttpAuth {
+    pub users: Vec<String>,
+    pub realm: String,
+    #[serde(rename = "removeHeader")]
+    #[serde(skip_serializing_if = "is_false")]
+    pub remove_header: bool,
+}
+
+#[derive(Serialize)]
+pub struct HttpForwardAuth {
+    pub address: String,
+    #[serde(rename = "trustForwardHeader")]
+    #[serde(skip_serializing_if = "is_false")]
+    pub trust_forward_header: bool,
+    #[serde(rename = "authResponseHeaders")]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub auth_response_headers: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct HttpRateLimit {
+    pub average: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub burst: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub period: Option<String>,
+    #[serde(rename = "sourceCriterion")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_criterion: Option<HttpSourceCriterion>,
+}
+
+#[derive(Serialize)]
+pub struct HttpInFlightReq {
+    pub amount: i32,
+    #[serde(rename = "sourceCriterion")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_criterion: Option<HttpSourceCriterion>,
+}
+
+/// What requests are grouped by for limits, the client IP if not set
+#[derive(Serialize, Clone)]
+pub struct HttpSourceCriterion {
+    #[serde(rename = "requestHeaderName")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_header_name: Option<String>,
+    #[serde(rename = "requestHost")]
+    #[serde(skip_serializing_if = "is_false")]
+    pub request_host: bool,
+}
+
+#[derive(Serialize)]
+pub struct HttpReplacePath {
+    pub path: String,
+}
+
+#[derive(Serialize)]
+pub struct HttpReplacePathRegex {
+    pub regex: String,
+    pub replacement: String,
+}
+
+#[derive(Serialize)]
+pub struct HttpStripPrefix {
+    pub prefixes: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct HttpAddPrefix {
+    pub prefix: String,
+}
+
+#[derive(Serialize)]
+pub struct HttpRedirectRegex {
+    pub regex: String,
+    pub replacement: String,
+    pub permanent: bool,
 }
 
 #[derive(Serialize)]
@@ -120,6 +378,11 @@ pub struct TcpConfig {
     pub routers: BTreeMap<String, TcpRouter>,
     #[serde(skip_serializing_if = "BTreeMap::is_empty")]
     pub services: BTreeMap<String, TcpService>,
+    #[serde(rename = "serversTransports")]
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub servers_transports: BTreeMap<String, TcpServersTransport>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub middlewares: BTreeMap<String, TcpMiddleware>,
 }
 
 impl TcpConfig {
@@ -127,11 +390,23 @@ impl TcpConfig {
         Self {
             routers: BTreeMap::new(),
             services: BTreeMap::new(),
+            servers_transports: BTreeMap::new(),
+            middlewares: BTreeMap::new(),
         }
     }
 
+    pub fn merge(&mut self, other: TcpConfig) {
+        self.routers.extend(other.routers);
+        self.services.extend(other.services);
+        self.servers_transports.extend(other.servers_transports);
+        self.middlewares.extend(other.middlewares);
+    }
+
     pub fn is_empty(&self) -> bool {
-        self.routers.is_empty() && self.services.is_empty()
+        self.routers.is_empty()
+            && self.services.is_empty()
+            && self.servers_transports.is_empty()
+            && self.middlewares.is_empty()
     }
 }
 
@@ -141,12 +416,40 @@ pub struct TcpRouter {
     pub service: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub priority: Option<i32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub middlewares: Vec<String>,
     pub tls: Option<TcpTls>,
 }
 
+#[derive(Serialize, Default)]
+pub struct TcpMiddleware {
+    #[serde(rename = "inFlightConn")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub in_flight_conn: Option<TcpInFlightConn>,
+    #[serde(rename = "ipAllowList")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ip_allow_list: Option<TcpIpAllowList>,
+}
+
+#[derive(Serialize)]
+pub struct TcpInFlightConn {
+    pub amount: i32,
+}
+
+#[derive(Serialize)]
+pub struct TcpIpAllowList {
+    #[serde(rename = "sourceRange")]
+    pub source_range: Vec<String>,
+}
+
 #[derive(Serialize)]
 pub struct TcpTls {
     pub passthrough: bool,
+    #[serde(rename = "certResolver")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cert_resolver: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub options: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -158,9 +461,101 @@ pub struct TcpService {
 #[derive(Serialize)]
 pub struct TcpLoadBalancer {
     pub servers: Vec<TcpServer>,
+    /// Deprecated since traefik v3.2 in favor of [`TcpServersTransport::proxy_protocol`]
+    #[serde(rename = "proxyProtocol")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxy_protocol: Option<ProxyProtocol>,
+    #[serde(rename = "serversTransport")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub servers_transport: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct TcpServersTransport {
+    #[serde(rename = "proxyProtocol")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxy_protocol: Option<ProxyProtocol>,
+}
+
+#[derive(Serialize, Clone, Copy)]
+pub struct ProxyProtocol {
+    pub version: i32,
 }
 
 #[derive(Serialize)]
 pub struct TcpServer {
     pub address: String,
 }
+
+#[derive(Serialize)]
+pub struct TlsConfig {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub certificates: Vec<TlsCertificate>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub stores: BTreeMap<String, TlsStore>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub options: BTreeMap<String, TlsOptions>,
+}
+
+impl TlsConfig {
+    pub fn new() -> Self {
+        Self {
+            certificates: Vec::new(),
+            stores: BTreeMap::new(),
+            options: BTreeMap::new(),
+        }
+    }
+
+    pub fn merge(&mut self, other: TlsConfig) {
+        self.certificates.extend(other.certificates);
+        self.stores.extend(other.stores);
+        self.options.extend(other.options);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.certificates.is_empty() && self.stores.is_empty() && self.options.is_empty()
+    }
+}
+
+/// Traefik accepts either a file path or the PEM content itself
+#[derive(Serialize, Clone)]
+pub struct TlsCertificate {
+    /// Name of the uploaded certificate, only used for the Kubernetes secret
+    #[serde(skip)]
+    pub name: String,
+    #[serde(rename = "certFile")]
+    pub cert_file: String,
+    #[serde(rename = "keyFile")]
+    pub key_file: String,
+}
+
+#[derive(Serialize)]
+pub struct TlsStore {
+    #[serde(rename = "defaultCertificate")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_certificate: Option<TlsCertificate>,
+}
+
+#[derive(Serialize)]
+pub struct TlsOptions {
+    #[serde(rename = "minVersion")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_version: Option<String>,
+    #[serde(rename = "cipherSuites")]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub cipher_suites: Vec<String>,
+    #[serde(rename = "sniStrict")]
+    pub sni_strict: bool,
+    #[serde(rename = "clientAuth")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_auth: Option<TlsClientAuth>,
+}
+
+#[derive(Serialize)]
+pub struct TlsClientAuth {
+    #[serde(rename = "caFiles")]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub ca_files: Vec<String>,
+    #[serde(rename = "clientAuthType")]
+    pub client_auth_type: String,
+}
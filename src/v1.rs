@@ -0,0 +1,505 @@
+//! Importer for the routes of the old V1 GUI.
+//!
+//! V1 has no documented data format, but it wrote all of its routes into a generated traefik
+//! config file, the one the migration notes in the Readme tell you to delete. The importer reads
+//! that file in the format of the traefik file provider, YAML or TOML by the file extension,
+//! so it only relies on what traefik documents: HTTP routers become HTTP routes, or HTTPS routes
+//! if they have `tls`, and TCP routers become TLS routes.
+
+use std::{collections::BTreeMap, path::Path};
+
+use diesel::{Connection, QueryDsl, RunQueryDsl, SqliteConnection};
+use rocket::{
+    form::Form,
+    response::{Flash, Redirect},
+    Build, Rocket, State,
+};
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{
+    config::ConfigState,
+    export_traefik_config,
+    http::HttpRoute,
+    https::{HttpsRoute, NO_CERT_RESOLVER},
+    schema::{http_routes, https_routes, tls_routes},
+    tls::TlsRoute,
+    DbConn,
+};
+
+#[derive(Debug, Error)]
+pub enum V1Error {
+    #[error("error reading V1 config from {0}: {1}")]
+    Read(String, std::io::Error),
+    #[error("error parsing V1 config: {0}")]
+    Yaml(#[from] serde_yaml::Error),
+    #[error("error parsing V1 config: {0}")]
+    Toml(#[from] toml::de::Error),
+    #[error("error importing V1 routes: {0}")]
+    Database(#[from] diesel::result::Error),
+}
+
+/// The parts of a traefik dynamic config the routes are read from
+#[derive(Deserialize, Default, Debug)]
+struct V1Config {
+    #[serde(default)]
+    http: V1Section,
+    #[serde(default)]
+    tcp: V1Section,
+}
+
+#[derive(Deserialize, Default, Debug)]
+struct V1Section {
+    #[serde(default)]
+    routers: BTreeMap<String, V1Router>,
+    #[serde(default)]
+    services: BTreeMap<String, V1Service>,
+}
+
+#[derive(Deserialize, Debug)]
+struct V1Router {
+    rule: String,
+    service: String,
+    #[serde(default)]
+    priority: Option<i32>,
+    #[serde(default)]
+    tls: Option<V1RouterTls>,
+}
+
+#[derive(Deserialize, Default, Debug)]
+struct V1RouterTls {
+    #[serde(default, rename = "certResolver")]
+    cert_resolver: Option<String>,
+    #[serde(default)]
+    passthrough: bool,
+}
+
+/// Only load balancers have a target, other service types are skipped
+#[derive(Deserialize, Debug)]
+struct V1Service {
+    #[serde(default, rename = "loadBalancer")]
+    load_balancer: Option<V1LoadBalancer>,
+}
+
+#[derive(Deserialize, Debug)]
+struct V1LoadBalancer {
+    #[serde(default)]
+    servers: Vec<V1Server>,
+}
+
+/// `url` for HTTP, `address` for TCP services
+#[derive(Deserialize, Debug)]
+struct V1Server {
+    #[serde(default)]
+    url: Option<String>,
+    #[serde(default)]
+    address: Option<String>,
+}
+
+/// The matchers of a router rule the routes of the GUI can express
+struct V1Rule {
+    host: String,
+    host_regex: bool,
+    prefix: Option<String>,
+}
+
+/// The arguments of every `matcher(...)` in the rule, e.g. both hosts of ``Host(`a`, `b`)``
+fn matcher_values<'a>(rule: &'a str, matcher: &str) -> Vec<&'a str> {
+    rule.split(matcher)
+        .skip(1)
+        // `Host` also splits `HostRegexp(` and `HostSNI(`, which don't continue with `(`
+        .filter_map(|rest| rest.strip_prefix('('))
+        .filter_map(|rest| rest.split(')').next())
+        .flat_map(|arguments| arguments.split('`').skip(1).step_by(2))
+        .collect()
+}
+
+impl V1Rule {
+    fn parse(rule: &str) -> Option<Self> {
+        let hosts = ["Host", "HostSNI"]
+            .iter()
+            .flat_map(|matcher| matcher_values(rule, matcher))
+            .collect::<Vec<_>>();
+        let regexes = matcher_values(rule, "HostRegexp");
+        let prefix = matcher_values(rule, "PathPrefix")
+            .first()
+            .map(|prefix| prefix.to_string());
+
+        match (hosts.as_slice(), regexes.as_slice()) {
+            ([_, ..], []) => Some(Self {
+                host: hosts.join(", "),
+                host_regex: false,
+                prefix,
+            }),
+            ([], [regex]) => Some(Self {
+                host: regex.to_string(),
+                host_regex: true,
+                prefix,
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl V1Section {
+    /// The first server of the load balancer behind the router
+    fn target(&self, router: &V1Router) -> Option<&V1Server> {
+        // routers reference services of the same file with or without `@file`
+        let name = router.service.trim_end_matches("@file");
+        self.services
+            .get(name)?
+            .load_balancer
+            .as_ref()?
+            .servers
+            .first()
+    }
+}
+
+pub struct V1Import {
+    pub http: usize,
+    pub https: usize,
+    pub tls: usize,
+    /// Routers whose rule or service the GUI can't express, or whose name is already taken
+    pub skipped: Vec<String>,
+}
+
+impl V1Import {
+    pub async fn run(path: &str, conn: &DbConn) -> Result<Self, V1Error> {
+        let config = V1Config::read(path)?;
+        conn.run(move |c| config.import(c)).await
+    }
+
+    pub fn summary(&self) -> String {
+        let mut summary = format!(
+            "Imported {} HTTP, {} HTTPS and {} TLS routes",
+            self.http, self.https, self.tls
+        );
+        if !self.skipped.is_empty() {
+            summary.push_str(&format!(", skipped {}", self.skipped.join(", ")));
+        }
+        summary
+    }
+}
+
+impl V1Config {
+    fn read(path: &str) -> Result<Self, V1Error> {
+        let serialized =
+            std::fs::read_to_string(path).map_err(|e| V1Error::Read(path.to_string(), e))?;
+
+        match Path::new(path)
+            .extension()
+            .and_then(|extension| extension.to_str())
+        {
+            Some("toml") => Ok(toml::from_str(&serialized)?),
+            _ => Ok(serde_yaml::from_str(&serialized)?),
+        }
+    }
+
+    fn import(self, c: &mut SqliteConnection) -> Result<V1Import, V1Error> {
+        let existing_http = http_routes::table
+            .select(http_routes::name)
+            .load::<String>(c)?;
+        let existing_https = https_routes::table
+            .select(https_routes::name)
+            .load::<String>(c)?;
+        let existing_tls = tls_routes::table
+            .select(tls_routes::name)
+            .load::<String>(c)?;
+
+        let mut skipped = Vec::new();
+        let mut http = Vec::new();
+        let mut https = Vec::new();
+        let mut tls = Vec::new();
+
+        for (name, router) in &self.http.routers {
+            let name = name.trim_end_matches("@file").to_string();
+            let target = self
+                .http
+                .target(router)
+                .and_then(|server| server.url.clone());
+            let (Some(rule), Some(target)) = (V1Rule::parse(&router.rule), target) else {
+                skipped.push(name);
+                continue;
+            };
+
+            match &router.tls {
+                None if !existing_http.contains(&name) => http.push(HttpRoute {
+                    id: None,
+                    enabled: true,
+                    name,
+                    priority: router.priority,
+                    target,
+                    host_regex: rule.host_regex,
+                    host: rule.host,
+                    prefix: rule.prefix,
+                    maintenance: false,
+                    maintenance_html: None,
+                    error_page: None,
+                    skip_default_middlewares: false,
+                    strip_prefix: false,
+                    add_prefix: None,
+                    path_regex: None,
+                    path_replacement: None,
+                    auth_realm: None,
+                    forward_auth: false,
+                    rate_limit_average: None,
+                    rate_limit_burst: None,
+                    rate_limit_period: None,
+                    limit_source: None,
+                    limit_header: None,
+                    in_flight_requests: None,
+                    fallback_target: None,
+                    health_check_path: None,
+                    health_check_interval: None,
+                }),
+                Some(router_tls) if !existing_https.contains(&name) => https.push(HttpsRoute {
+                    id: None,
+                    enabled: true,
+                    name,
+                    priority: router.priority,
+                    target,
+                    host_regex: rule.host_regex,
+                    host: rule.host,
+                    prefix: rule.prefix,
+                    https_redirect: false,
+                    allow_http_acme: false,
+                    // a router without resolver used the certificates of the store
+                    cert_resolver: Some(
+                        router_tls
+                            .cert_resolver
+                            .clone()
+                            .unwrap_or_else(|| NO_CERT_RESOLVER.to_string()),
+                    ),
+                    tls_domains: None,
+                    tls_options: None,
+                    maintenance: false,
+                    maintenance_html: None,
+                    error_page: None,
+                    skip_default_middlewares: false,
+                    header_profile: None,
+                    strip_prefix: false,
+                    add_prefix: None,
+                    path_regex: None,
+                    path_replacement: None,
+                    auth_realm: None,
+                    forward_auth: false,
+                    rate_limit_average: None,
+                    rate_limit_burst: None,
+                    rate_limit_period: None,
+                    limit_source: None,
+                    limit_header: None,
+                    in_flight_requests: None,
+                    mirrors: None,
+                    fallback_target: None,
+                    health_check_path: None,
+                    health_check_interval: None,
+                }),
+                _ => skipped.push(name),
+            }
+        }
+
+        for (name, router) in &self.tcp.routers {
+            let name = name.trim_end_matches("@file").to_string();
+            let target = self
+                .tcp
+                .target(router)
+                .and_then(|server| server.address.clone());
+            let (Some(rule), Some(target)) = (V1Rule::parse(&router.rule), target) else {
+                skipped.push(name);
+                continue;
+            };
+            if existing_tls.contains(&name) {
+                skipped.push(name);
+                continue;
+            }
+
+            let router_tls = router.tls.as_ref();
+            tls.push(TlsRoute {
+                id: None,
+                enabled: true,
+                name,
+                priority: router.priority,
+                target,
+                host_regex: rule.host_regex,
+                host: rule.host,
+                acme_http_passthrough: None,
+                https_redirect: false,
+                terminate_tls: router_tls.is_some_and(|tls| !tls.passthrough),
+                cert_resolver: router_tls.and_then(|tls| tls.cert_resolver.clone()),
+                tls_options: None,
+                proxy_protocol: None,
+                in_flight_connections: None,
+                ip_allow_list: None,
+            });
+        }
+
+        http.iter_mut().for_each(HttpRoute::cleanup);
+        https.iter_mut().for_each(HttpsRoute::cleanup);
+        tls.iter_mut().for_each(TlsRoute::cleanup);
+
+        c.transaction(|c| {
+            for route in &http {
+                diesel::insert_into(http_routes::table)
+                    .values(route)
+                    .execute(c)?;
+            }
+            for route in &https {
+                diesel::insert_into(https_routes::table)
+                    .values(route)
+                    .execute(c)?;
+            }
+            for route in &tls {
+                diesel::insert_into(tls_routes::table)
+                    .values(route)
+                    .execute(c)?;
+            }
+            diesel::result::QueryResult::Ok(())
+        })?;
+
+        Ok(V1Import {
+            http: http.len(),
+            https: https.len(),
+            tls: tls.len(),
+            skipped,
+        })
+    }
+}
+
+/// Imports the V1 routes on startup if `import_v1` is set in the Rocket config,
+/// e.g. via `ROCKET_IMPORT_V1=/app/traefik/config.yml`.
+/// The import only runs while there are no routes yet, so restarting the container doesn't duplicate them.
+pub async fn import_on_startup(rocket: Rocket<Build>) -> Rocket<Build> {
+    let Ok(path) = rocket.figment().extract_inner::<String>("import_v1") else {
+        return rocket;
+    };
+
+    let conn = DbConn::get_one(&rocket).await.expect("database connection");
+
+    let existing = HttpRoute::count(&conn).await.unwrap_or(0)
+        + HttpsRoute::count(&conn).await.unwrap_or(0)
+        + TlsRoute::count(&conn).await.unwrap_or(0);
+    if existing > 0 {
+        info!("Skipping V1 import, there are already routes configured");
+        return rocket;
+    }
+
+    match V1Import::run(&path, &conn).await {
+        Ok(import) => info!("{} from V1", import.summary()),
+        Err(e) => error!("V1 import failed: {}", e),
+    }
+
+    rocket
+}
+
+#[derive(FromForm)]
+pub struct V1ImportForm {
+    path: String,
+}
+
+/// Routes whose name already exists are skipped, so importing twice doesn't duplicate them
+#[post("/import_v1", data = "<import_form>")]
+pub async fn import(
+    import_form: Form<V1ImportForm>,
+    conn: DbConn,
+    config: &State<ConfigState>,
+) -> Flash<Redirect> {
+    match V1Import::run(import_form.path.trim(), &conn).await {
+        Ok(import) => {
+            export_traefik_config(&conn, &config.config()).await;
+            Flash::success(Redirect::to("/config"), import.summary())
+        }
+        Err(e) => {
+            error!("V1 import failed: {}", e);
+            Flash::error(Redirect::to("/config"), e.to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use diesel_migrations::MigrationHarness;
+
+    use super::*;
+
+    #[test]
+    fn imports_generated_config() {
+        let mut c = SqliteConnection::establish(":memory:").unwrap();
+        c.run_pending_migrations(crate::MIGRATIONS).unwrap();
+
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/v1/config.yml");
+        let import = V1Config::read(path).unwrap().import(&mut c).unwrap();
+        assert_eq!((import.http, import.https, import.tls), (2, 1, 1));
+        assert_eq!(import.skipped, ["dashboard"]);
+
+        let http = http_routes::table.load::<HttpRoute>(&mut c).unwrap();
+        let http = http
+            .iter()
+            .map(|r| {
+                (
+                    r.name.as_str(),
+                    r.host.as_str(),
+                    r.host_regex,
+                    r.prefix.as_deref(),
+                    r.target.as_str(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            http,
+            [
+                (
+                    "api",
+                    "example.com",
+                    false,
+                    Some("/api"),
+                    "http://10.0.0.6:8080"
+                ),
+                ("wiki", "^wiki\\..+$", true, None, "http://10.0.0.8"),
+            ]
+        );
+
+        let https = https_routes::table.load::<HttpsRoute>(&mut c).unwrap();
+        let https = https
+            .iter()
+            .map(|r| {
+                (
+                    r.name.as_str(),
+                    r.host.as_str(),
+                    r.target.as_str(),
+                    r.cert_resolver.as_deref(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            https,
+            [(
+                "nextcloud",
+                "cloud.example.com, files.example.com",
+                "http://10.0.0.5:80",
+                Some("le")
+            )]
+        );
+
+        let tls = tls_routes::table.load::<TlsRoute>(&mut c).unwrap();
+        let tls = tls
+            .iter()
+            .map(|r| {
+                (
+                    r.name.as_str(),
+                    r.host.as_str(),
+                    r.target.as_str(),
+                    r.terminate_tls,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(tls, [("mail", "mail.example.com", "10.0.0.7:993", false)]);
+
+        // importing again skips the routes that already exist
+        let import = V1Config::read(path).unwrap().import(&mut c).unwrap();
+        assert_eq!((import.http, import.https, import.tls), (0, 0, 0));
+        assert_eq!(
+            import.skipped,
+            ["api", "dashboard", "nextcloud", "wiki", "mail"]
+        );
+    }
+}
@@ -0,0 +1,128 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rustls::{
+    crypto::ring::sign::any_supported_type,
+    pki_types::{pem::PemObject, PrivateKeyDer},
+};
+use serde::Serialize;
+use x509_parser::{extensions::GeneralName, pem::Pem};
+
+/// Certificates expiring in less than this many days are flagged
+pub const EXPIRY_WARNING_DAYS: i64 = 30;
+
+/// The parts of a certificate shown in the GUI
+#[derive(Serialize, Clone, Debug)]
+pub struct CertificateInfo {
+    pub subject: String,
+    pub issuer: String,
+    pub sans: Vec<String>,
+    /// unix timestamp in seconds
+    pub not_after: i64,
+    pub days_left: i64,
+    pub expires_soon: bool,
+}
+
+pub fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| now.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+fn leaf_certificate(pem: &[u8]) -> Result<Pem, String> {
+    Pem::iter_from_buffer(pem)
+        .filter_map(Result::ok)
+        .find(|pem| pem.label == "CERTIFICATE")
+        .ok_or_else(|| "no certificate found".to_string())
+}
+
+/// Checks that the private key belongs to the leaf certificate of the PEM chain
+pub fn key_matches(cert_pem: &[u8], key_pem: &[u8]) -> Result<(), String> {
+    let pem = leaf_certificate(cert_pem)?;
+    let cert = pem.parse_x509().map_err(|e| e.to_string())?;
+
+    let key = PrivateKeyDer::from_pem_slice(key_pem)
+        .map_err(|e| format!("invalid private key: {}", e))?;
+    let signing_key =
+        any_supported_type(&key).map_err(|e| format!("unsupported private key: {}", e))?;
+    let public_key = signing_key.public_key().ok_or("unsupported private key")?;
+
+    if public_key.as_ref() == cert.public_key().raw {
+        Ok(())
+    } else {
+        Err("the private key doesn't match the certificate".into())
+    }
+}
+
+impl CertificateInfo {
+    /// Parses the first certificate of a PEM chain, which is the leaf certificate
+    pub fn from_pem(pem: &[u8]) -> Result<Self, String> {
+        let pem = leaf_certificate(pem)?;
+        let cert = pem.parse_x509().map_err(|e| e.to_string())?;
+
+        let sans = cert
+            .subject_alternative_name()
+            .ok()
+            .flatten()
+            .map(|san| {
+                san.value
+                    .general_names
+                    .iter()
+                    .filter_map(|name| match name {
+                        GeneralName::DNSName(name) => Some(name.to_string()),
+                        _ => None,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let not_after = cert.validity().not_after.timestamp();
+        let days_left = (not_after - now()) / (24 * 60 * 60);
+
+        Ok(Self {
+            subject: cert.subject().to_string(),
+            issuer: cert.issuer().to_string(),
+            sans,
+            not_after,
+            days_left,
+            expires_soon: days_left < EXPIRY_WARNING_DAYS,
+        })
+    }
+
+    /// Checks if the certificate is valid for the host, including wildcard names
+    pub fn covers(&self, host: &str) -> bool {
+        let host = host.trim().to_lowercase();
+
+        self.sans.iter().any(|name| {
+            let name = name.to_lowercase();
+            match name.strip_prefix("*.") {
+                Some(domain) => host
+                    .split_once('.')
+                    .is_some_and(|(label, rest)| !label.is_empty() && rest == domain),
+                None => name == host,
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CERT: &[u8] = include_bytes!("../tests/fixtures/x509/cert.pem");
+    const KEY: &[u8] = include_bytes!("../tests/fixtures/x509/key.pem");
+    const OTHER_KEY: &[u8] = include_bytes!("../tests/fixtures/x509/other_key.pem");
+
+    #[test]
+    fn accepts_matching_key() {
+        assert_eq!(key_matches(CERT, KEY), Ok(()));
+    }
+
+    #[test]
+    fn rejects_other_key() {
+        assert_eq!(
+            key_matches(CERT, OTHER_KEY),
+            Err("the private key doesn't match the certificate".into())
+        );
+    }
+}